@@ -1,53 +1,202 @@
 use std::{
-    cmp::Ordering,
     env::{self},
-    error::Error,
     fmt::Debug,
     io::{self, Write},
+    path::PathBuf,
     process,
 };
 
-use crate::task_file::TaskFile;
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+use tsk::{
+    date::{self, Recurrence},
+    error::Error,
+    id::{Selection, Target, TaskId},
+    task_file::{Position, PrintOptions, Priority, SortKey, TaskFile, MAX_BACKUPS},
+};
 
 const DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
 const NAME: &str = env!("CARGO_PKG_NAME");
 
+/// Environment variable holding the path of the task file to use.
+const FILE_ENV_VAR: &str = "TSK_FILE";
+
+/// A command along with the arguments it was given.
+#[derive(Debug)]
+pub enum Command {
+    Print,
+    Add {
+        contents: String,
+        /// Task to add a subtask to, given with `--subtask`.
+        parent: Option<Target>,
+    },
+    Do(Selection),
+    Undo(Selection),
+    Due {
+        ids: Selection,
+        due: Option<NaiveDate>,
+    },
+    Prio {
+        ids: Selection,
+        priority: Option<Priority>,
+    },
+    Recur {
+        ids: Selection,
+        recurrence: Option<Recurrence>,
+    },
+    Tag {
+        ids: Selection,
+        tags: Vec<String>,
+    },
+    Untag {
+        ids: Selection,
+        tags: Vec<String>,
+    },
+    Sort(SortKey),
+    Move {
+        ids: Selection,
+        to: Target,
+    },
+    Swap(Target, Target),
+    Append {
+        ids: Selection,
+        text: String,
+    },
+    Edit {
+        ids: Selection,
+        text: String,
+    },
+    Delete(Selection),
+    Note(Target),
+    Show(Target),
+    Find(Regex),
+    Start(Target),
+    Stop,
+    Time,
+    Clear,
+    /// How many operations to step through.
+    Revert(usize),
+    Redo(usize),
+    History,
+    /// Text searched for in the archive.
+    Archive(String),
+    /// Which archived task to bring back, 1 is the oldest.
+    Unarchive(usize),
+    Backups,
+    /// Which backup to bring back, 1 is the newest.
+    Restore(usize),
+    /// `None` for printing the lists.
+    Lists(Option<ListAction>),
+    Init,
+}
+
+/// What the `lists` command does besides printing the lists.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ListAction {
+    New(String),
+    Rename(String, String),
+    Delete(String),
+}
+
 #[derive(Debug)]
 pub struct Cli {
     /// Make `print` output colored.
     pub colored_output: bool,
 
     /// The command to run.
-    pub command: String,
+    pub command: Command,
 
     /// Where to add a new task.
-    pub add_to: String,
+    pub add_to: Position,
+
+    /// Name of the task list to use, `None` for the default list.
+    pub list: Option<String>,
+
+    /// Path of a task file to use instead of the selected list.
+    pub file: Option<PathBuf>,
+
+    /// Make `print` show the unique ids of tasks.
+    pub show_uids: bool,
+
+    /// For `print`, only tasks matching it are printed.
+    pub pattern: Option<Regex>,
 
-    /// Comma separeted list of ids/subids.
-    pub task_ids: String,
+    /// Make `find` and `--match` treat their text as a regular expression.
+    pub regex: bool,
 
-    /// Single id for commands that move tasks.
-    pub move_id: String,
+    /// Make `find` and `--match` ignore the case of letters.
+    pub ignore_case: bool,
 
-    /// Text for commands that modify a task's content.
-    pub contents: String,
+    /// For `print`, only tasks that aren't done are printed.
+    pub pending: bool,
+
+    /// For `print`, only tasks that are done are printed.
+    pub done: bool,
+
+    /// Make `print` show the tasks passing any filter instead of all of them.
+    pub any: bool,
+
+    /// Make `print` show only top level tasks.
+    pub top_level_only: bool,
+
+    /// Due date given to `add`, for `print` only tasks due by then are printed.
+    pub due: Option<NaiveDate>,
+
+    /// Priority given to `add`, for `print` only tasks with at least this priority
+    /// are printed.
+    pub priority: Option<Priority>,
+
+    /// Recurrence given to `add`.
+    pub recurrence: Option<Recurrence>,
+
+    /// For `print`, only tasks with all of these tags are printed.
+    pub tags: Vec<String>,
+
+    /// Key `print` and `sort` sort tasks by, `None` for their position.
+    pub sort_by: Option<SortKey>,
+
+    /// For `print`, only tasks completed since then are printed.
+    pub done_since: Option<NaiveDate>,
+
+    /// For `print`, only tasks created since then are printed.
+    pub created_since: Option<NaiveDate>,
+
+    /// For `print`, only tasks modified since then are printed.
+    pub modified_since: Option<NaiveDate>,
+}
 
-    /// Should id lists be allowed.
-    pub allow_id_list: bool,
+impl Default for Cli {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cli {
     pub fn new() -> Self {
         Self {
             colored_output: true,
-            command: "print".to_owned(),
-            add_to: "top".to_owned(),
-            task_ids: String::new(),
-            move_id: String::new(),
-            contents: String::new(),
-            allow_id_list: false,
+            command: Command::Print,
+            add_to: Position::Top,
+            list: None,
+            file: None,
+            show_uids: false,
+            pattern: None,
+            regex: false,
+            ignore_case: false,
+            pending: false,
+            done: false,
+            any: false,
+            top_level_only: false,
+            due: None,
+            priority: None,
+            recurrence: None,
+            tags: Vec::new(),
+            sort_by: None,
+            done_since: None,
+            created_since: None,
+            modified_since: None,
         }
     }
 
@@ -59,6 +208,14 @@ impl Cli {
 
         Usage: {NAME} [Options] [Command] [Sub-Options] [Args]
 
+        Tasks are selected by their position, ex.: `2`, `2.1` for a subtask or `2.1.3`.
+        Positions can count from the end, `-1` or `last` being the last task and `3.-2`
+        the second to last subtask of 3, and `3.*` selects every subtask of 3.
+        They can also be selected by a prefix of their unique id, ex.: `@3fa2`,
+        which doesn't change when tasks are moved around, by a tag, ex.: `+backend`,
+        which selects every task with that tag, or with `done` and `pending`.
+        Commands taking several ids accept lists and ranges, ex.: `1,3..5,7.2` or `2.1..2.4`.
+
         Options:
             --generate-shell-completions <prompt> <current_word>,<position>
                 Generate shell completions
@@ -68,6 +225,8 @@ impl Cli {
                 Print the program version
             --no-color  -c
                 Don't make the output colored
+            --uids      -u
+                Print the unique ids of tasks
             --all       -a
                 Shortcut for selecting all tasks.
                 Not used by commands that use only a single id
@@ -75,29 +234,130 @@ impl Cli {
                 Used by `add`.
                 Values: top, bot[tom]
             --subtask   -s=<parent_id>
-                Used by `add` to add a task as a subtask.
+                Used by `add` to add a task as a subtask, subtasks can be nested at any depth.
+            --due       -d=<when>
+                Used by `add` to give the task a due date, and by `print` to only print
+                the tasks due on or before <when>.
+                Values: 2026-11-03, today, tomorrow, fri[day], +3d, +2w, +1m, +1y, -3d
+            --priority  -p=<level>
+                Used by `add` to give the task a priority, and by `print` to only print
+                the tasks with at least <level> priority.
+                Values: h[igh], m[edium], l[ow]
+            --every     -r=<rule>
+                Used by `add` to make the task recur, see `recur`
+            --done-since=<when>
+                Used by `print` to only print the tasks completed on or after <when>.
+                A weekday is its last occurrence, ex.: `--done-since=mon`, `--done-since=-1w`
+            --created-since=<when>
+                Used by `print` to only print the tasks created on or after <when>
+            --modified-since=<when>
+                Used by `print` to only print the tasks modified on or after <when>
+            --pending
+                Used by `print` to only print the tasks that aren't done
+            --done
+                Used by `print` to only print the tasks that are done
+            --match=<text>
+                Used by `print` to only print the tasks containing <text>,
+                `--regex` and `--ignore-case` work with it like with `find`
+            --tag=<tag>
+                Used by `print` to only print the tasks with <tag>, same as `print +<tag>`
+            --or
+                Used by `print` to print the tasks passing any of the filters,
+                by default they must pass all of them.
+                Ex.: `tsk print --or --due=today --priority=high`
+            --top-level-only
+                Used by `print` to only print top level tasks, subtasks aren't looked at
+            --by=<key>
+                Used by `print` to sort tasks, their ids stay the same, and by `sort`.
+                Values: due, priority
+            --regex
+                Used by `find` and `--match` to search for a regular expression, ex.: `tsk find --regex '^fix'`
+            --ignore-case -i
+                Used by `find` and `--match` to ignore the case of letters
+            --list=<name>
+                Use the task list <name> instead of the default one
+            --file      -f=<path>
+                Use the task file at <path>, it's created if it doesn't exist.
+                The `TSK_FILE` environment variable can be used instead
 
         Commands:
-            print
-                Print tasks, default when not passing any args
+            print   [+tags]
+                Print tasks, default when not passing any args.
+                With tags, only the tasks that have all of them are printed.
+                Tasks keep their ids, and the parents of the printed tasks are printed too
             add     <task>
-                Add a new task.
+                Add a new task, words like `+tag` in it become tags.
             do      <task_ids>
                 Mark task(s) as done
             undo    <task_ids>
                 Unmark task(s) as done
-            move    <task_id> <new_task_id>
-                Move a task to a new location
+            due     <task_ids> <when>
+                Set the due date of task(s), `none` removes it.
+                Overdue tasks and tasks due today are highlighted
+            prio    <task_ids> <level>
+                Set the priority of task(s), `none` removes it.
+                Printed as `!!!` for high, `!!` for medium and `!` for low
+            recur   <task_ids> <rule>
+                Make task(s) recur, `none` stops them from recurring.
+                When a recurring task is done it comes back undone, along with its subtasks,
+                due on its next occurrence. `clear` doesn't delete recurring tasks.
+                Rules: every day, every 3 days, every week, every 2 months, every fri, every 1st
+            tag     <task_ids> <tags>
+                Add tags to task(s), ex.: `tsk tag 2 backend +urgent`
+            untag   <task_ids> <tags>
+                Remove tags from task(s)
+            sort    --by=<key>
+                Reorder the tasks by <key>, at every depth
+            move    <task_ids> <new_task_id>
+                Move task(s) to a new location, keeping them together in their order.
+                The first of them ends up at <new_task_id>
             swap    <task_id> <other_task_id>
                 Swap the places of two tasks
-            append  <task_id> <text>
-                Append <text> to an existing task
-            edit    <task_id> <new_text>
-                Replace the text of the task with <new_text>
+            append  <task_ids> <text>
+                Append <text> to existing task(s)
+            edit    <task_ids> <new_text>
+                Replace the text of task(s) with <new_text>
             delete  <task_ids>
-                Delete tasks
+                Delete task(s), ids refer to the tasks as they were before deleting any
+            note    <task_id>
+                Write the notes of a task with $EDITOR, or read them from stdin when piped.
+                Tasks with notes are marked with `[notes]`
+            show    <task_id>
+                Print everything about a task, including its notes and when it was
+                created, last modified and completed
+            find    <text>
+                Print the tasks and subtasks containing <text>, with the ids they can be
+                selected by
+            start   <task_id>
+                Start timing the work on a task, stopping the timer of any other task.
+                The task being timed is marked with its running time
+            stop
+                Stop the running timer
+            time
+                Print the time spent on each task and on each day
             clear   
-                Delete all tasks that are marked as done
+                Move all tasks that are marked as done to the archive.
+                Recurring tasks are kept
+            revert  [n]
+                Revert the last [n] operations, 1 by default
+            redo    [n]
+                Redo the last [n] reverted operations, 1 by default
+            history
+                Print the operations that can be reverted, newest first
+            archive [text]
+                Print the archived tasks, or only the ones containing [text]
+            unarchive <n>
+                Move the <n>th archived task back to the task list
+            backups
+                Print the backups of the task file, newest first.
+                A backup is kept before every change, up to {MAX_BACKUPS}
+            restore <n>
+                Replace the task file with its <n>th newest backup
+            lists   [new|rename|delete] [name] [new_name]
+                Print the task lists, or create/rename/delete a list
+            init
+                Create a `.tsk.json` task file for the project in the current directory.
+                Inside a project, its task file is used instead of the global one
         "
         );
     }
@@ -106,16 +366,14 @@ impl Cli {
     /// `args` should be formatted this way: "$prompt $current_word,$position"
     /// Ex.: "tsk ad -t = top 'hi' ad,1"
     pub fn generate_shell_completions(args: Vec<String>) -> Result<String, &'static str> {
-        if args.is_empty() {
-            return Ok("".to_string());
-        } else if args.contains(&"--generate-shell-completions".to_string()) {
+        if args.is_empty() || args.contains(&"--generate-shell-completions".to_string()) {
             return Ok("".to_string());
         }
 
         let no_opts: Vec<String> = {
             let mut a: Vec<String> = args
                 .iter()
-                .filter(|a| !a.starts_with('-'))
+                .filter(|a| !Self::is_option(a))
                 .map(|a| a.to_string())
                 .collect();
 
@@ -146,34 +404,73 @@ impl Cli {
 
         // Find the position of the '=' that the user is at.
         let opt = if current_word == "=" {
-            &args.get(position - 1).unwrap_or_else(|| process::exit(1))
+            args.get(position - 1).unwrap_or_else(|| process::exit(1))
         } else if args.get(position - 1).is_some_and(|a| a == "=") {
-            &args.get(position - 2).unwrap_or_else(|| process::exit(1))
+            args.get(position - 2).unwrap_or_else(|| process::exit(1))
         } else {
             ""
         };
 
         if opt == "-t" || opt == "--add-to" {
             return Ok("top bottom".to_string());
+        } else if [
+            "-d",
+            "--due",
+            "--done-since",
+            "--created-since",
+            "--modified-since",
+        ]
+        .contains(&opt)
+        {
+            return Ok("today tomorrow mon tue wed thu fri sat sun".to_string());
+        } else if opt == "-p" || opt == "--priority" {
+            return Ok("high medium low".to_string());
+        } else if opt == "-r" || opt == "--every" {
+            return Ok("day week month year mon tue wed thu fri sat sun 1st 15th".to_string());
+        } else if opt == "--by" {
+            return Ok("due priority".to_string());
+        } else if opt == "--list" {
+            let lists = TaskFile::get_lists_dir().map(|d| TaskFile::get_lists(&d));
+            return Ok(lists.unwrap_or_default().join(" "));
         }
 
         if current_word.starts_with("--") {
             return Ok(
-                "--help --version --no-color --all --add-to --subtask --generate-shell-completions"
+                "--help --version --no-color --uids --all --add-to --subtask --due --priority --every --done-since --created-since --modified-since --pending --done --match --tag --or --top-level-only --by --regex --ignore-case --list --file --generate-shell-completions"
                     .to_string(),
             );
         } else if current_word.starts_with('-') {
-            return Ok("-h -v -c -a -t -s".to_string());
+            return Ok("-h -v -c -u -a -t -s -d -p -r -i -f".to_string());
         } else if position == 1 || no_opts.get(1).is_some_and(|a| a == current_word) {
-            return Ok("print add do undo move swap append edit delete clear".to_string());
+            return Ok(
+                "print add do undo due prio recur tag untag sort move swap append edit delete note show find start stop time clear revert redo history archive unarchive backups restore lists init".to_string(),
+            );
+        }
+
+        if no_opts.get(1).is_some_and(|a| a == "lists") && position == 2 {
+            return Ok("new rename delete".to_string());
         }
 
         if no_opts.get(1).is_some_and(|a| a == "edit") && position == 3 {
             let id = no_opts.get(2).unwrap_or_else(|| process::exit(1));
-            let id = id.parse::<usize>().unwrap_or_else(|_| process::exit(1));
-
-            let tf = TaskFile::load();
-            if let Some(cont) = tf.get_task_contents(&id.to_string()) {
+            let id = id.parse::<TaskId>().unwrap_or_else(|_| process::exit(1));
+
+            // `--list = <name>` is split into 3 words by the shell.
+            let get_opt = |opts: &[&str]| {
+                args.iter()
+                    .position(|a| opts.contains(&a.as_str()))
+                    .and_then(|i| args.get(i + 2))
+                    .map_or("", |v| v.as_str())
+            };
+            let list = Some(get_opt(&["--list"])).filter(|l| !l.is_empty());
+            let file = match get_opt(&["-f", "--file"]) {
+                "" if list.is_none() => Self::get_env_file(),
+                "" => None,
+                f => Some(PathBuf::from(f)),
+            };
+
+            let tf = TaskFile::load(list, file.as_deref()).ok();
+            if let Some(cont) = tf.and_then(|tf| tf.get_task_contents(&id)) {
                 return Ok(format!("'{cont}'"));
             }
             return Ok("".to_string());
@@ -182,19 +479,42 @@ impl Cli {
         Ok("".to_string())
     }
 
+    /// Build the pattern `find` searches for, `text` is matched literally unless
+    /// `--regex` is used.
+    fn build_pattern(&self, text: &str) -> Result<Regex, Error> {
+        let pattern = if self.regex {
+            text.to_owned()
+        } else {
+            regex::escape(text)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map_err(|e| Error::InvalidOption(format!("Invalid regex `{text}`\nErr: {e}")))
+    }
+
+    /// Whether `arg` is an option rather than an argument. Ids counting from the end, like
+    /// `-1` or `-3..-1`, are arguments.
+    fn is_option(arg: &str) -> bool {
+        arg.starts_with('-') && !arg[1..].starts_with(|c: char| c.is_ascii_digit())
+    }
+
     /// Parse cmd line arguments.
     /// When debugging the vector's 1st value should be an empty string, since that's
     /// where the program's path would be normally, and that value is skipped over.
-    pub fn parse_args(dbg_args: Option<Vec<String>>) -> Result<Self, String> {
+    pub fn parse_args(dbg_args: Option<Vec<String>>) -> Result<Self, Error> {
         let mut cli = Self::new();
         let options = if let Some(a) = dbg_args.clone() {
-            let a: Vec<String> = a.into_iter().filter(|a| a.starts_with('-')).collect();
+            let a: Vec<String> = a.into_iter().filter(|a| Self::is_option(a)).collect();
             a.into_iter()
         } else {
-            let a: Vec<String> = env::args().filter(|a| a.starts_with('-')).collect();
+            let a: Vec<String> = env::args().filter(|a| Self::is_option(a)).collect();
             a.into_iter()
         };
 
+        let mut match_text = String::new();
+        // Tasks given with `--all` or `--subtask`.
+        let mut selection = None;
         for o in options {
             match o.as_str() {
                 "-h" | "--help" => {
@@ -206,7 +526,14 @@ impl Cli {
                     process::exit(0)
                 }
                 "-c" | "--no-color" => cli.colored_output = false,
-                "-a" | "--all" => cli.task_ids = "all".to_string(),
+                "-u" | "--uids" => cli.show_uids = true,
+                "-a" | "--all" => selection = Some(Selection::All),
+                "--regex" => cli.regex = true,
+                "-i" | "--ignore-case" => cli.ignore_case = true,
+                "--pending" => cli.pending = true,
+                "--done" => cli.done = true,
+                "--or" => cli.any = true,
+                "--top-level-only" => cli.top_level_only = true,
 
                 "--generate-shell-completions" => {
                     let args: Vec<String> = env::args()
@@ -225,189 +552,349 @@ impl Cli {
                     }
                 }
 
-                _ if !o.contains('=') => return Err(format!("Unknown option `{o}`")),
+                _ if !o.contains('=') => {
+                    return Err(Error::InvalidOption(format!("Unknown option `{o}`")))
+                }
                 _ => (),
             }
             if !o.contains('=') {
                 continue;
             }
-            let v: Vec<&str> = o.split('=').collect();
+            let v: Vec<&str> = o.splitn(2, '=').collect();
             match v[0] {
-                "-t" | "--add-to" => {
-                    if v[1] != "top" && v[1] != "bot" && v[1] != "bottom" {
-                        return Err(format!("Invalid option value `{}`", v[1]));
+                "-t" | "--add-to" => cli.add_to = v[1].parse()?,
+                "-s" | "--subtask" => {
+                    if v[1].is_empty() {
+                        return Err(Error::InvalidId("Please provide an id".to_owned()));
                     }
-                    cli.add_to = v[1].to_string();
+                    selection = Some(v[1].parse()?);
                 }
-                "-s" | "--subtask" => {
+                "--list" => {
+                    if !TaskFile::is_valid_list_name(v[1]) {
+                        return Err(Error::InvalidOption(format!(
+                            "Invalid list name `{}`",
+                            v[1]
+                        )));
+                    }
+                    cli.list = Some(v[1].to_string());
+                }
+                "-f" | "--file" => {
+                    if v[1].is_empty() {
+                        return Err(Error::InvalidOption("Please provide a path".to_owned()));
+                    }
+                    cli.file = Some(PathBuf::from(v[1]));
+                }
+                "-d" | "--due" => cli.due = Some(date::parse_date(v[1], date::today())?),
+                "-p" | "--priority" => cli.priority = Some(v[1].parse()?),
+                "-r" | "--every" => cli.recurrence = Some(v[1].parse()?),
+                "--done-since" => {
+                    cli.done_since = Some(date::parse_past_date(v[1], date::today())?)
+                }
+                "--created-since" => {
+                    cli.created_since = Some(date::parse_past_date(v[1], date::today())?)
+                }
+                "--modified-since" => {
+                    cli.modified_since = Some(date::parse_past_date(v[1], date::today())?)
+                }
+                "--match" => {
                     if v[1].is_empty() {
-                        return Err(format!("Please provide an id"));
+                        return Err(Error::InvalidOption(
+                            "Please provide the text to match".to_owned(),
+                        ));
                     }
-                    cli.task_ids = v[1].to_string();
+                    // Built once all options are known, they may change how it's matched.
+                    match_text = v[1].to_string();
                 }
+                "--tag" => cli.tags.push(Self::parse_tag(v[1])?),
+                "--by" => cli.sort_by = Some(v[1].parse()?),
                 _ => {
-                    return Err(format!("Unknown option `{}`", v[0]));
+                    return Err(Error::InvalidOption(format!("Unknown option `{}`", v[0])));
                 }
             }
         }
 
+        if !match_text.is_empty() {
+            cli.pattern = Some(cli.build_pattern(&match_text)?);
+        }
+
+        if cli.file.is_some() && cli.list.is_some() {
+            return Err(Error::InvalidOption(
+                "Options `--file` and `--list` can't be used together".to_owned(),
+            ));
+        } else if cli.file.is_none() && cli.list.is_none() {
+            cli.file = Self::get_env_file();
+        }
+
         // Use debug arguments, ortherwise normal arguments.
         let mut args = if let Some(a) = dbg_args {
-            let a: Vec<String> = a.into_iter().filter(|a| !a.starts_with('-')).collect();
+            let a: Vec<String> = a.into_iter().filter(|a| !Self::is_option(a)).collect();
             a.into_iter()
         } else {
-            let a: Vec<String> = env::args().filter(|a| !a.starts_with('-')).collect();
+            let a: Vec<String> = env::args().filter(|a| !Self::is_option(a)).collect();
             a.into_iter()
         };
         args.next(); // Path of executable not needed.
 
-        let arg = args.next();
-        if arg.is_none() {
+        let Some(arg) = args.next() else {
             return Ok(cli);
-        }
-
-        let arg = arg.unwrap();
-        match arg.as_str() {
-            "print" => return Ok(cli),
-            "clear" => {
-                cli.command = arg;
-                return Ok(cli);
+        };
+        cli.command = match arg.as_str() {
+            "print" => {
+                for a in args {
+                    cli.tags.push(Self::parse_tag(&a)?);
+                }
+                Command::Print
             }
-            "do" | "undo" => {
-                cli.command = arg;
-                cli.allow_id_list = true
+            "clear" => Command::Clear,
+            "init" => Command::Init,
+            "history" => Command::History,
+            "backups" => Command::Backups,
+            "stop" => Command::Stop,
+            "time" => Command::Time,
+            "revert" | "redo" => {
+                let steps = match args.next() {
+                    None => 1,
+                    Some(n) => match n.parse::<usize>() {
+                        Ok(0) | Err(_) => {
+                            return Err(Error::InvalidOption(format!(
+                                "Invalid number of operations `{n}`"
+                            )))
+                        }
+                        Ok(n) => n,
+                    },
+                };
+                if arg == "revert" {
+                    Command::Revert(steps)
+                } else {
+                    Command::Redo(steps)
+                }
             }
-            "add" | "move" | "swap" | "edit" | "append" | "delete" => {
-                cli.command = arg;
+            "sort" => Command::Sort(cli.sort_by.ok_or_else(|| {
+                Error::InvalidOption("Missing option `--by` for `sort`".to_owned())
+            })?),
+            "restore" => {
+                let n = args.next().ok_or_else(|| {
+                    Error::InvalidOption("Missing arguments for `restore`".to_owned())
+                })?;
+                match n.parse::<usize>() {
+                    Ok(n) if (1..=MAX_BACKUPS).contains(&n) => Command::Restore(n),
+                    _ => {
+                        return Err(Error::InvalidOption(format!(
+                            "Invalid backup `{n}`, use 1 to {MAX_BACKUPS}"
+                        )))
+                    }
+                }
+            }
+            "find" => {
+                let text = args.collect::<Vec<String>>().join(" ");
+                if text.is_empty() {
+                    return Err(Error::InvalidOption(
+                        "Missing arguments for `find`".to_owned(),
+                    ));
+                }
+                Command::Find(cli.build_pattern(&text)?)
+            }
+            "archive" => Command::Archive(args.collect::<Vec<String>>().join(" ")),
+            "unarchive" => {
+                let n = args.next().ok_or_else(|| {
+                    Error::InvalidOption("Missing arguments for `unarchive`".to_owned())
+                })?;
+                match n.parse::<usize>() {
+                    Ok(0) | Err(_) => {
+                        return Err(Error::InvalidOption(format!("Invalid archived task `{n}`")))
+                    }
+                    Ok(n) => Command::Unarchive(n),
+                }
+            }
+            "lists" => Command::Lists(Self::parse_lists_args(args)?),
+            "add" | "do" | "undo" | "due" | "prio" | "recur" | "tag" | "untag" | "move"
+            | "swap" | "edit" | "append" | "delete" | "note" | "show" | "start" => {
+                Self::parse_task_command(&arg, args, selection)?
             }
             _ => {
-                return Err(format!("Unknown command `{arg}`"));
+                return Err(Error::InvalidOption(format!("Unknown command `{arg}`")));
             }
-        }
+        };
+        Ok(cli)
+    }
 
-        let arg = args.next().unwrap_or_default();
-        if arg.is_empty() && cli.task_ids != "all" {
-            return Err(format!("Missing arguments for `{}`", cli.command));
+    /// Parse the arguments of the commands working on tasks. `selection` holds the tasks
+    /// given with `--all` or `--subtask`.
+    fn parse_task_command(
+        name: &str,
+        args: impl Iterator<Item = String>,
+        selection: Option<Selection>,
+    ) -> Result<Command, Error> {
+        let missing_args = || Error::InvalidOption(format!("Missing arguments for `{name}`"));
+        let single_id_error = || Error::InvalidId("Command only accepts a single id".to_owned());
+        // An empty argument, ex.: `tsk edit 2 ""`, is a missing one.
+        let mut args = args.filter(|a| !a.is_empty());
+        if name == "add" {
+            let contents = args.next().ok_or_else(missing_args)?;
+            let parent = selection
+                .map(|s| s.as_single().cloned().ok_or_else(single_id_error))
+                .transpose()?;
+            return Ok(Command::Add { contents, parent });
         }
 
-        if cli.command == "add" {
-            cli.contents = arg;
-            return Ok(cli);
+        let single_id = matches!(name, "swap" | "note" | "show" | "start");
+        let all = selection == Some(Selection::All);
+        if all && single_id {
+            return Err(Error::InvalidId(
+                "Flag `--all` not allowed for single task commands".to_owned(),
+            ));
         }
-        if cli.task_ids != "all" {
-            cli.task_ids = arg;
+        // `--all` stands in for the ids, the arguments after them are parsed the same way.
+        let mut args = all.then(|| "all".to_owned()).into_iter().chain(args);
+        let ids: Selection = args.next().ok_or_else(missing_args)?.parse()?;
+        if single_id && ids.as_single().is_none() {
+            return Err(single_id_error());
         }
-        match cli.command.as_str() {
-            "do" | "undo" => return Ok(cli),
+        let missing_second =
+            || Error::InvalidOption(format!("Missing second argument for `{name}`"));
+
+        match name {
+            "do" => return Ok(Command::Do(ids)),
+            "undo" => return Ok(Command::Undo(ids)),
+            "delete" => return Ok(Command::Delete(ids)),
+            "note" | "show" | "start" => {
+                let id = ids.as_single().cloned().ok_or_else(single_id_error)?;
+                return Ok(match name {
+                    "note" => Command::Note(id),
+                    "show" => Command::Show(id),
+                    _ => Command::Start(id),
+                });
+            }
 
-            "delete" if cli.task_ids == "all" => {
-                return Err(format!("Flag `--all` not allowed for single task commands"))
+            "due" | "prio" => {
+                let value = args.next().ok_or_else(missing_second)?;
+                return match (name, value.as_str()) {
+                    ("due", "none") => Ok(Command::Due { ids, due: None }),
+                    ("due", w) => Ok(Command::Due {
+                        ids,
+                        due: Some(date::parse_date(w, date::today())?),
+                    }),
+                    (_, "none") => Ok(Command::Prio {
+                        ids,
+                        priority: None,
+                    }),
+                    (_, p) => Ok(Command::Prio {
+                        ids,
+                        priority: Some(p.parse()?),
+                    }),
+                };
+            }
+            "recur" => {
+                let rule = args.collect::<Vec<String>>().join(" ");
+                let recurrence = match rule.as_str() {
+                    "" => return Err(missing_second()),
+                    "none" => None,
+                    r => Some(r.parse()?),
+                };
+                return Ok(Command::Recur { ids, recurrence });
+            }
+            "tag" | "untag" => {
+                let tags = args
+                    .map(|a| Self::parse_tag(&a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if tags.is_empty() {
+                    return Err(Error::InvalidOption(format!("Missing tags for `{name}`")));
+                }
+                return Ok(if name == "tag" {
+                    Command::Tag { ids, tags }
+                } else {
+                    Command::Untag { ids, tags }
+                });
             }
-            "delete" => return Ok(cli),
             _ => (),
         }
 
-        let arg = args.next().unwrap_or_default();
-        if cli.task_ids == "all" {
-            return Err(format!("Flag `--all` not allowed for single task commands"));
-        } else if arg.is_empty() {
-            return Err(format!("Missing second argument for `{}`", cli.command));
-        }
+        let arg = args.next().ok_or_else(missing_second)?;
 
-        match cli.command.as_str() {
-            "move" | "swap" if cli.task_ids == arg => {
-                return Err(format!("Please provide different ids"));
+        match name {
+            "move" | "swap" => {
+                let to: Target = arg.parse()?;
+                if ids.as_single() == Some(&to) {
+                    return Err(Error::InvalidId("Please provide different ids".to_owned()));
+                }
+                match ids.as_single() {
+                    Some(id) if name == "swap" => Ok(Command::Swap(id.clone(), to)),
+                    _ => Ok(Command::Move { ids, to }),
+                }
             }
-
-            "move" | "swap" => cli.move_id = arg,
-            "edit" | "append" => cli.contents = arg,
-            _ => (),
+            "edit" => Ok(Command::Edit { ids, text: arg }),
+            _ => Ok(Command::Append { ids, text: arg }),
         }
-        Ok(cli)
     }
 
-    /// Takes a list of ids and returns it dedupped and sorted.
-    pub fn parse_id_list(ids: &str) -> Result<String, Box<dyn Error>> {
-        let mut v: Vec<String> = ids.split(',').map(|i| i.to_string()).collect();
-        if !ids.contains('.') {
-            let err = v.iter().find_map(|s| s.parse::<usize>().err());
-            if let Some(e) = err {
-                return Err(Box::new(e));
-            }
-
-            v.sort_by(|a, b| {
-                a.parse::<usize>()
-                    .unwrap()
-                    .cmp(&b.parse::<usize>().unwrap())
-            });
+    /// Path of the task file given by the `TSK_FILE` environment variable, if set.
+    fn get_env_file() -> Option<PathBuf> {
+        env::var_os(FILE_ENV_VAR)
+            .filter(|f| !f.is_empty())
+            .map(PathBuf::from)
+    }
 
-            v.dedup();
-            return Ok(v.join(","));
-        }
-        let err = v.iter().find_map(|s| s.parse::<f32>().err());
-        if let Some(e) = err {
-            return Err(Box::new(e));
+    /// Parse a tag given as an argument, the leading `+` is optional.
+    fn parse_tag(arg: &str) -> Result<String, Error> {
+        let tag = arg.strip_prefix('+').unwrap_or(arg);
+        if !TaskFile::is_valid_tag(tag) {
+            return Err(Error::InvalidOption(format!("Invalid tag `{arg}`")));
         }
-
-        v.sort_by(|a, b| {
-            let a = a.parse::<f32>().unwrap();
-            let b = b.parse::<f32>().unwrap();
-
-            if a >= b {
-                return Ordering::Greater;
-            } else {
-                return Ordering::Less;
-            }
-        });
-
-        v.dedup();
-        Ok(v.join(","))
+        Ok(tag.to_owned())
     }
 
-    /// Takes a range pattern and returns a list of ids.
-    pub fn parse_id_range(ids: &str) -> Result<String, Box<dyn Error>> {
-        let ids: Vec<String> = ids.split("..").map(|i| i.to_string()).collect();
-
-        // Ranges of subids are not allowed, so take both parent numbers
-        // and make a range of them.
-        let start: usize = if ids[0].contains('.') {
-            let v: Vec<&str> = ids[0].split('.').collect();
-            v[0].parse()?
-        } else {
-            ids[0].parse()?
-        };
-
-        let end: usize = if ids[1].contains('.') {
-            let v: Vec<&str> = ids[1].split('.').collect();
-            v[0].parse()?
-        } else {
-            ids[1].parse()?
-        };
+    /// How `print` should show the tasks, the filters only apply to the `print` command.
+    pub fn get_print_options(&self) -> PrintOptions {
+        let print = matches!(self.command, Command::Print);
+        PrintOptions {
+            colored: self.colored_output,
+            show_uids: self.show_uids,
+            sort_by: self.sort_by,
+            due_before: self.due.filter(|_| print),
+            min_priority: self.priority.filter(|_| print),
+            tags: if print { self.tags.clone() } else { Vec::new() },
+            done_since: self.done_since.filter(|_| print),
+            created_since: self.created_since.filter(|_| print),
+            modified_since: self.modified_since.filter(|_| print),
+            pending: self.pending && print,
+            done: self.done && print,
+            pattern: self.pattern.clone().filter(|_| print),
+            any: self.any && print,
+            top_level_only: self.top_level_only && print,
+        }
+    }
 
-        // Allow for 5..8 and 8..5
-        let range = if start < end {
-            start..=end
-        } else {
-            end..=start
+    /// Parse the arguments of the `lists` command.
+    fn parse_lists_args(
+        mut args: impl Iterator<Item = String>,
+    ) -> Result<Option<ListAction>, Error> {
+        let action = args.next().unwrap_or_default();
+        let expected = match action.as_str() {
+            "" => return Ok(None),
+            "new" | "delete" => 1,
+            "rename" => 2,
+            _ => {
+                return Err(Error::InvalidOption(format!(
+                    "Unknown action `{action}` for `lists`"
+                )))
+            }
         };
 
-        let mut out: Vec<String> = range.map(|i| i.to_string()).collect();
-        let l = out.len() - 1;
-
-        // Replace the start and end values with the proper ids.
-        if ids[0].contains('.') {
-            // Determine where `ids[0]` (the `start` id with a subid) should be put.
-            // start..=end - start < end
-            // end..=start - start > end
-
-            let i = if start > end { l } else { 0 };
-            out[i] = ids[0].clone();
+        let mut names: Vec<String> = args.take(expected).collect();
+        if names.len() != expected {
+            return Err(Error::InvalidOption(format!(
+                "Missing arguments for `lists {action}`"
+            )));
         }
-        if ids[1].contains('.') {
-            let i = if start > end { 0 } else { l };
-            out[i] = ids[1].clone();
+        if let Some(n) = names.iter().find(|n| !TaskFile::is_valid_list_name(n)) {
+            return Err(Error::InvalidOption(format!("Invalid list name `{n}`")));
         }
-        return Ok(out.join(","));
+        let first = names.remove(0);
+        Ok(Some(match action.as_str() {
+            "new" => ListAction::New(first),
+            "delete" => ListAction::Delete(first),
+            _ => ListAction::Rename(first, names.remove(0)),
+        }))
     }
 }
 
@@ -432,21 +919,21 @@ mod test {
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "print add do undo move swap append edit delete clear"
+            "print add do undo due prio recur tag untag sort move swap append edit delete note show find start stop time clear revert redo history archive unarchive backups restore lists init"
         );
 
         let comp = get_comp("tsk ad ad,1");
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "print add do undo move swap append edit delete clear"
+            "print add do undo due prio recur tag untag sort move swap append edit delete note show find start stop time clear revert redo history archive unarchive backups restore lists init"
         );
 
         let comp = get_comp("tsk ze ze,1");
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "print add do undo move swap append edit delete clear"
+            "print add do undo due prio recur tag untag sort move swap append edit delete note show find start stop time clear revert redo history archive unarchive backups restore lists init"
         );
     }
 
@@ -456,28 +943,28 @@ mod test {
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "print add do undo move swap append edit delete clear"
+            "print add do undo due prio recur tag untag sort move swap append edit delete note show find start stop time clear revert redo history archive unarchive backups restore lists init"
         );
 
         let comp = get_comp("tsk -a sw sw,2");
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "print add do undo move swap append edit delete clear"
+            "print add do undo due prio recur tag untag sort move swap append edit delete note show find start stop time clear revert redo history archive unarchive backups restore lists init"
         );
 
         let comp = get_comp("tsk -a -s = 2    ,5");
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "print add do undo move swap append edit delete clear"
+            "print add do undo due prio recur tag untag sort move swap append edit delete note show find start stop time clear revert redo history archive unarchive backups restore lists init"
         );
 
         let comp = get_comp("tsk -a -s = 2 pr pr,5");
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "print add do undo move swap append edit delete clear"
+            "print add do undo due prio recur tag untag sort move swap append edit delete note show find start stop time clear revert redo history archive unarchive backups restore lists init"
         );
     }
 
@@ -485,15 +972,15 @@ mod test {
     fn test_short_option_completion() {
         let comp = get_comp("tsk - -,1");
         assert!(comp.is_ok());
-        assert_eq!(comp.unwrap(), "-h -v -c -a -t -s");
+        assert_eq!(comp.unwrap(), "-h -v -c -u -a -t -s -d -p -r -i -f");
 
         let comp = get_comp("tsk -c -c,1");
         assert!(comp.is_ok());
-        assert_eq!(comp.unwrap(), "-h -v -c -a -t -s");
+        assert_eq!(comp.unwrap(), "-h -v -c -u -a -t -s -d -p -r -i -f");
 
         let comp = get_comp("tsk -z -z,1");
         assert!(comp.is_ok());
-        assert_eq!(comp.unwrap(), "-h -v -c -a -t -s");
+        assert_eq!(comp.unwrap(), "-h -v -c -u -a -t -s -d -p -r -i -f");
     }
 
     #[test]
@@ -502,24 +989,31 @@ mod test {
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "--help --version --no-color --all --add-to --subtask --generate-shell-completions"
+            "--help --version --no-color --uids --all --add-to --subtask --due --priority --every --done-since --created-since --modified-since --pending --done --match --tag --or --top-level-only --by --regex --ignore-case --list --file --generate-shell-completions"
         );
 
         let comp = get_comp("tsk --h --h,1");
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "--help --version --no-color --all --add-to --subtask --generate-shell-completions"
+            "--help --version --no-color --uids --all --add-to --subtask --due --priority --every --done-since --created-since --modified-since --pending --done --match --tag --or --top-level-only --by --regex --ignore-case --list --file --generate-shell-completions"
         );
 
         let comp = get_comp("tsk --z --z,1");
         assert!(comp.is_ok());
         assert_eq!(
             comp.unwrap(),
-            "--help --version --no-color --all --add-to --subtask --generate-shell-completions"
+            "--help --version --no-color --uids --all --add-to --subtask --due --priority --every --done-since --created-since --modified-since --pending --done --match --tag --or --top-level-only --by --regex --ignore-case --list --file --generate-shell-completions"
         );
     }
 
+    #[test]
+    fn test_lists_completion() {
+        let comp = get_comp("tsk lists    ,2");
+        assert!(comp.is_ok());
+        assert_eq!(comp.unwrap(), "new rename delete");
+    }
+
     #[test]
     fn test_add_to_option_completion() {
         let comp = get_comp("tsk -t =    ,3");
@@ -542,10 +1036,10 @@ mod test {
     #[test]
     fn test_edit_completion() {
         let comp = get_comp("tsk edit 2    ,3");
-        let tf = TaskFile::load();
+        let tf = TaskFile::load(None, Cli::get_env_file().as_deref()).unwrap();
         assert!(comp.is_ok());
 
-        let cont = if let Some(c) = tf.get_task_contents("2") {
+        let cont = if let Some(c) = tf.get_task_contents(&TaskId::top(1)) {
             format!("'{c}'")
         } else {
             "".to_string()
@@ -578,60 +1072,171 @@ mod test {
 
     // // ARG PARSING
     /// `args` ex.: "tsk add -t=top 'hello world' "
-    fn get_cli(args: &str) -> Result<Cli, String> {
+    fn get_cli(args: &str) -> Result<Cli, Error> {
         let args = args.trim();
         let a = args.split(' ').map(|s| s.to_owned()).collect();
         Cli::parse_args(Some(a))
     }
 
+    /// The tasks given to the command parsed by `cli` as they were written, empty when
+    /// there are none.
+    fn get_ids(cli: &Cli) -> String {
+        match &cli.command {
+            Command::Add { parent, .. } => parent.as_ref().map(|p| p.to_string()),
+            Command::Do(ids)
+            | Command::Undo(ids)
+            | Command::Delete(ids)
+            | Command::Due { ids, .. }
+            | Command::Prio { ids, .. }
+            | Command::Recur { ids, .. }
+            | Command::Tag { ids, .. }
+            | Command::Untag { ids, .. }
+            | Command::Move { ids, .. }
+            | Command::Append { ids, .. }
+            | Command::Edit { ids, .. } => Some(ids.to_string()),
+            Command::Swap(id, _) | Command::Note(id) | Command::Show(id) | Command::Start(id) => {
+                Some(id.to_string())
+            }
+            _ => None,
+        }
+        .unwrap_or_default()
+    }
+
+    /// The text given to `add`, `edit` or `append`.
+    fn get_text(cli: &Cli) -> &str {
+        match &cli.command {
+            Command::Add { contents: text, .. }
+            | Command::Edit { text, .. }
+            | Command::Append { text, .. } => text,
+            _ => "",
+        }
+    }
+
     // OPTIONS
     #[test]
     fn test_flag_nocolor() {
         let cli = get_cli("tsk -c");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().colored_output, false);
+        assert!(!cli.unwrap().colored_output);
+    }
+
+    #[test]
+    fn test_flag_uids() {
+        let cli = get_cli("tsk -u");
+        assert!(cli.is_ok());
+        assert!(cli.unwrap().show_uids);
     }
 
     #[test]
     fn test_flag_all() {
         let cli = get_cli("tsk do -a");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().task_ids, "all");
+        assert_eq!(get_ids(&cli.unwrap()), "all");
+
+        let cli = get_cli("tsk move -a 2");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+        assert_eq!(get_ids(&cli), "all");
+        assert!(matches!(cli.command, Command::Move { to, .. } if to.to_string() == "2"));
+
+        let cli = get_cli("tsk edit -a");
+        assert!(cli.is_err());
     }
 
     #[test]
     fn test_flag_all_fail_on_single_id_cmd() {
-        let cli = get_cli("tsk edit -a");
+        let cli = get_cli("tsk show -a");
         assert!(cli.is_err());
         assert_eq!(
             cli.err().unwrap(),
-            "Flag `--all` not allowed for single task commands"
+            Error::InvalidId("Flag `--all` not allowed for single task commands".to_owned())
         );
 
-        let cli = get_cli("tsk delete -a");
+        let cli = get_cli("tsk swap -a 2");
         assert!(cli.is_err());
     }
 
+    #[test]
+    fn test_id_lists_for_task_changes() {
+        let cli = get_cli("tsk delete 1,3..5");
+        assert!(cli.is_ok());
+        assert_eq!(get_ids(&cli.unwrap()), "1,3..5");
+
+        let cli = get_cli("tsk edit 2.* test");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+        assert_eq!(get_ids(&cli), "2.*");
+        assert_eq!(get_text(&cli), "test");
+
+        let cli = get_cli("tsk append -a test");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+        assert_eq!(get_ids(&cli), "all");
+        assert_eq!(get_text(&cli), "test");
+
+        let cli = get_cli("tsk move 2..4 1");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+        assert_eq!(get_ids(&cli), "2..4");
+        assert!(matches!(cli.command, Command::Move { to, .. } if to.to_string() == "1"));
+
+        let cli = get_cli("tsk swap 2,3 1");
+        assert_eq!(
+            cli.err().unwrap(),
+            Error::InvalidId("Command only accepts a single id".to_owned())
+        );
+    }
+
     #[test]
     fn test_option_addto() {
         let cli = get_cli("tsk -t=top");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().add_to, "top");
+        assert_eq!(cli.unwrap().add_to, Position::Top);
 
         let cli = get_cli("tsk -t=bot");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().add_to, "bot");
+        assert_eq!(cli.unwrap().add_to, Position::Bottom);
 
         let cli = get_cli("tsk -t=bottom");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().add_to, "bottom");
+        assert_eq!(cli.unwrap().add_to, Position::Bottom);
     }
 
     #[test]
     fn test_option_subtask() {
-        let cli = get_cli("tsk -s=1.2");
+        let cli = get_cli("tsk add -s=1.2 test");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().task_ids, "1.2")
+        assert_eq!(get_ids(&cli.unwrap()), "1.2")
+    }
+
+    #[test]
+    fn test_option_list() {
+        let cli = get_cli("tsk --list=release");
+        assert!(cli.is_ok());
+        assert_eq!(cli.unwrap().list.as_deref(), Some("release"));
+
+        let cli = get_cli("tsk --list=");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk --list=re.lease");
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_option_file() {
+        let cli = get_cli("tsk -f=/tmp/tasks.json");
+        assert!(cli.is_ok());
+        assert_eq!(cli.unwrap().file, Some(PathBuf::from("/tmp/tasks.json")));
+
+        let cli = get_cli("tsk --file=/tmp/a=b.json");
+        assert!(cli.is_ok());
+        assert_eq!(cli.unwrap().file, Some(PathBuf::from("/tmp/a=b.json")));
+
+        let cli = get_cli("tsk --file=");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk --file=/tmp/tasks.json --list=release");
+        assert!(cli.is_err());
     }
 
     #[test]
@@ -663,15 +1268,19 @@ mod test {
     fn test_cmds_without_ids() {
         let cli = get_cli("tsk print");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().command, "print");
+        assert!(matches!(cli.unwrap().command, Command::Print));
 
         let cli = get_cli("tsk");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().command, "print");
+        assert!(matches!(cli.unwrap().command, Command::Print));
 
         let cli = get_cli("tsk clear");
         assert!(cli.is_ok());
-        assert_eq!(cli.unwrap().command, "clear");
+        assert!(matches!(cli.unwrap().command, Command::Clear));
+
+        let cli = get_cli("tsk init");
+        assert!(cli.is_ok());
+        assert!(matches!(cli.unwrap().command, Command::Init));
     }
 
     #[test]
@@ -680,17 +1289,17 @@ mod test {
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "add");
-        assert_eq!(cli.contents, "test");
-        assert_eq!(cli.task_ids, "2");
+        assert!(matches!(cli.command, Command::Add { .. }));
+        assert_eq!(get_text(&cli), "test");
+        assert_eq!(get_ids(&cli), "2");
 
         let cli = get_cli("tsk add test");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "add");
-        assert_eq!(cli.contents, "test");
-        assert_eq!(cli.task_ids, String::new());
+        assert!(matches!(cli.command, Command::Add { .. }));
+        assert_eq!(get_text(&cli), "test");
+        assert_eq!(get_ids(&cli), "");
     }
 
     #[test]
@@ -699,15 +1308,15 @@ mod test {
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "do");
-        assert_eq!(cli.task_ids, "2");
+        assert!(matches!(cli.command, Command::Do(_)));
+        assert_eq!(get_ids(&cli), "2");
 
         let cli = get_cli("tsk undo 2");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "undo");
-        assert_eq!(cli.task_ids, "2");
+        assert!(matches!(cli.command, Command::Undo(_)));
+        assert_eq!(get_ids(&cli), "2");
 
         let cli = get_cli("tsk do ");
         assert!(cli.is_err());
@@ -715,54 +1324,71 @@ mod test {
         let cli = get_cli("tsk undo ");
         assert!(cli.is_err());
 
-        let cli = get_cli("tsk do 2,3..2.45.1,2,35,10a");
+        let cli = get_cli("tsk do 2,3.2,35,@a1");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "do");
-        assert_eq!(cli.task_ids, "2,3..2.45.1,2,35,10a");
+        assert!(matches!(cli.command, Command::Do(_)));
+        assert_eq!(get_ids(&cli), "2,3.2,35,@a1");
 
-        let cli = get_cli("tsk undo 2,3..2.45.1,2,35,10a");
+        let cli = get_cli("tsk undo 3..2.45.1");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "undo");
-        assert_eq!(cli.task_ids, "2,3..2.45.1,2,35,10a");
+        assert!(matches!(cli.command, Command::Undo(_)));
+        assert_eq!(get_ids(&cli), "3..2.45.1");
+
+        // Malformed ids are rejected before the task file is loaded.
+        let cli = get_cli("tsk do 2,3..2.45.1,2,35,10a");
+        assert!(matches!(cli, Err(Error::InvalidId(_))));
     }
 
     #[test]
-    fn test_move_cmds() {
-        let cli = get_cli("tsk move 2 3");
+    fn test_ids_from_the_end() {
+        let cli = get_cli("tsk do -1");
         assert!(cli.is_ok());
-        let cli = cli.unwrap();
+        assert_eq!(get_ids(&cli.unwrap()), "-1");
 
-        assert_eq!(cli.command, "move");
-        assert_eq!(cli.task_ids, "2");
-        assert_eq!(cli.move_id, "3");
+        let cli = get_cli("tsk -c do -3..-1,2.*");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+        assert!(!cli.colored_output);
+        assert_eq!(get_ids(&cli), "-3..-1,2.*");
 
-        let cli = get_cli("tsk swap 2 4");
+        let cli = get_cli("tsk edit -2 test");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
+        assert_eq!(get_ids(&cli), "-2");
+        assert_eq!(get_text(&cli), "test");
 
-        assert_eq!(cli.command, "swap");
-        assert_eq!(cli.task_ids, "2");
-        assert_eq!(cli.move_id, "4");
+        let cli = get_cli("tsk move last 1");
+        assert!(cli.is_ok());
+        assert_eq!(get_ids(&cli.unwrap()), "-1");
+    }
 
-        let cli = get_cli("tsk move a b");
+    #[test]
+    fn test_move_cmds() {
+        let cli = get_cli("tsk move 2 3");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "move");
-        assert_eq!(cli.task_ids, "a");
-        assert_eq!(cli.move_id, "b");
+        assert!(matches!(cli.command, Command::Move { .. }));
+        assert_eq!(get_ids(&cli), "2");
+        assert!(matches!(&cli.command, Command::Move { to, .. } if to.to_string() == "3"));
 
-        let cli = get_cli("tsk swap a b");
+        let cli = get_cli("tsk swap 2 4");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "swap");
-        assert_eq!(cli.task_ids, "a");
-        assert_eq!(cli.move_id, "b");
+        assert!(matches!(cli.command, Command::Swap(..)));
+        assert_eq!(get_ids(&cli), "2");
+        assert!(matches!(&cli.command, Command::Swap(_, to) if to.to_string() == "4"));
+
+        let cli = get_cli("tsk move a b");
+        assert!(matches!(cli, Err(Error::InvalidId(_))));
+
+        let cli = get_cli("tsk swap a b");
+        assert!(matches!(cli, Err(Error::InvalidId(_))));
 
         let cli = get_cli("tsk move ");
         assert!(cli.is_err());
@@ -789,105 +1415,470 @@ mod test {
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "edit");
-        assert_eq!(cli.task_ids, "2");
-        assert_eq!(cli.contents, "test");
+        assert!(matches!(cli.command, Command::Edit { .. }));
+        assert_eq!(get_ids(&cli), "2");
+        assert_eq!(get_text(&cli), "test");
 
         let cli = get_cli("tsk append 2 test");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "append");
-        assert_eq!(cli.task_ids, "2");
-        assert_eq!(cli.contents, "test");
+        assert!(matches!(cli.command, Command::Append { .. }));
+        assert_eq!(get_ids(&cli), "2");
+        assert_eq!(get_text(&cli), "test");
 
         let cli = get_cli("tsk edit a test");
+        assert!(matches!(cli, Err(Error::InvalidId(_))));
+
+        let cli = get_cli("tsk append a test");
+        assert!(matches!(cli, Err(Error::InvalidId(_))));
+
+        let cli = get_cli("tsk edit ");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk append ");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk edit 2 ");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk append 3 ");
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_lists_cmds() {
+        let cli = get_cli("tsk lists");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "edit");
-        assert_eq!(cli.task_ids, "a");
-        assert_eq!(cli.contents, "test");
+        assert!(matches!(cli.command, Command::Lists(None)));
 
-        let cli = get_cli("tsk append a test");
+        let cli = get_cli("tsk lists new release");
         assert!(cli.is_ok());
         let cli = cli.unwrap();
 
-        assert_eq!(cli.command, "append");
-        assert_eq!(cli.task_ids, "a");
-        assert_eq!(cli.contents, "test");
+        assert!(matches!(
+            cli.command,
+            Command::Lists(Some(ListAction::New(n))) if n == "release"
+        ));
 
-        let cli = get_cli("tsk edit ");
+        let cli = get_cli("tsk lists rename release old-release");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert!(matches!(
+            cli.command,
+            Command::Lists(Some(ListAction::Rename(old, new))) if old == "release" && new == "old-release"
+        ));
+
+        let cli = get_cli("tsk lists rename release");
         assert!(cli.is_err());
 
-        let cli = get_cli("tsk append ");
+        let cli = get_cli("tsk lists delete");
         assert!(cli.is_err());
 
-        let cli = get_cli("tsk edit 2 ");
+        let cli = get_cli("tsk lists new a.b");
         assert!(cli.is_err());
 
-        let cli = get_cli("tsk append 3 ");
+        let cli = get_cli("tsk lists bob");
         assert!(cli.is_err());
     }
 
     #[test]
-    fn test_unknow_command() {
-        let cli = get_cli("tsk bob 50");
-        assert!(cli.is_err())
+    fn test_due_cmd() {
+        let cli = get_cli("tsk due 1,2 2026-11-03");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert_eq!(get_ids(&cli), "1,2");
+        assert!(matches!(
+            cli.command,
+            Command::Due { due, .. } if due == NaiveDate::from_ymd_opt(2026, 11, 3)
+        ));
+
+        let cli = get_cli("tsk due -a tomorrow");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert_eq!(get_ids(&cli), "all");
+        assert!(matches!(
+            cli.command,
+            Command::Due { due, .. } if due == Some(date::today() + chrono::Days::new(1))
+        ));
+
+        let cli = get_cli("tsk due 1 none");
+        assert!(cli.is_ok());
+        assert!(matches!(
+            cli.unwrap().command,
+            Command::Due { due: None, .. }
+        ));
+
+        let cli = get_cli("tsk due 1");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk due 1 someday");
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_due_and_by_options() {
+        let cli = get_cli("tsk add -d=+3d test");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert_eq!(cli.due, Some(date::today() + chrono::Days::new(3)));
+        assert_eq!(cli.get_print_options().due_before, None);
+
+        let cli = get_cli("tsk print --due=2026-11-03 --by=due");
+        assert!(cli.is_ok());
+        let opts = cli.unwrap().get_print_options();
+
+        assert_eq!(opts.due_before, NaiveDate::from_ymd_opt(2026, 11, 3));
+        assert_eq!(opts.sort_by, Some(SortKey::Due));
+
+        let cli = get_cli("tsk --due=later");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk --by=size");
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_prio_cmd() {
+        let cli = get_cli("tsk prio 1.2 high");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert_eq!(get_ids(&cli), "1.2");
+        assert!(matches!(
+            cli.command,
+            Command::Prio {
+                priority: Some(Priority::High),
+                ..
+            }
+        ));
+
+        let cli = get_cli("tsk prio -a l");
+        assert!(cli.is_ok());
+        assert!(matches!(
+            cli.unwrap().command,
+            Command::Prio {
+                priority: Some(Priority::Low),
+                ..
+            }
+        ));
+
+        let cli = get_cli("tsk prio 1 none");
+        assert!(cli.is_ok());
+        assert!(matches!(
+            cli.unwrap().command,
+            Command::Prio { priority: None, .. }
+        ));
+
+        let cli = get_cli("tsk prio 1 urgent");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk add -p=m test");
+        assert!(cli.is_ok());
+        assert_eq!(cli.unwrap().priority, Some(Priority::Medium));
+    }
+
+    #[test]
+    fn test_tag_cmds() {
+        let cli = get_cli("tsk tag 1,2 backend +urgent");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert_eq!(get_ids(&cli), "1,2");
+        assert!(matches!(cli.command, Command::Tag { tags, .. } if tags == ["backend", "urgent"]));
+
+        let cli = get_cli("tsk untag -a +urgent");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert_eq!(get_ids(&cli), "all");
+        assert!(matches!(cli.command, Command::Untag { tags, .. } if tags == ["urgent"]));
+
+        let cli = get_cli("tsk tag 1");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk tag 1 +1st");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk print +backend");
+        assert!(cli.is_ok());
+        assert_eq!(cli.unwrap().get_print_options().tags, ["backend"]);
+
+        let cli = get_cli("tsk print backend!");
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_note_and_show_cmds() {
+        let cli = get_cli("tsk note 2.1");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert!(matches!(cli.command, Command::Note(_)));
+        assert_eq!(get_ids(&cli), "2.1");
+
+        let cli = get_cli("tsk show 3");
+        assert!(cli.is_ok());
+        assert!(matches!(cli.unwrap().command, Command::Show(_)));
+
+        let cli = get_cli("tsk show -a");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk note");
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_timer_cmds() {
+        let cli = get_cli("tsk start 2.1");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert!(matches!(cli.command, Command::Start(_)));
+        assert_eq!(get_ids(&cli), "2.1");
+
+        let cli = get_cli("tsk start -a");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk start");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk stop");
+        assert!(cli.is_ok());
+        assert!(matches!(cli.unwrap().command, Command::Stop));
+
+        let cli = get_cli("tsk time");
+        assert!(cli.is_ok());
+        assert!(matches!(cli.unwrap().command, Command::Time));
     }
 
-    // // ID PARSING
     #[test]
-    fn test_parse_list_normal_ids() {
-        let res = Cli::parse_id_list("1,2,3,4,4,3,12,5");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1,2,3,4,5,12".to_string());
+    fn test_since_options() {
+        let cli = get_cli("tsk print --done-since=2026-10-12 --created-since=-1w");
+        assert!(cli.is_ok());
+        let opts = cli.unwrap().get_print_options();
+
+        assert_eq!(opts.done_since, NaiveDate::from_ymd_opt(2026, 10, 12));
+        assert_eq!(
+            opts.created_since,
+            Some(date::today() - chrono::Days::new(7))
+        );
+        assert_eq!(opts.modified_since, None);
+
+        let cli = get_cli("tsk print --modified-since=mon");
+        assert!(cli.is_ok());
+        assert!(cli.unwrap().modified_since.unwrap() <= date::today());
+
+        let cli = get_cli("tsk print --done-since=");
+        assert!(cli.is_err());
+
+        let cli =
+            get_cli("tsk do 1 --done-since=2026-10-12 --created-since=-1w --modified-since=mon");
+        assert!(cli.is_ok());
+        let opts = cli.unwrap().get_print_options();
+        assert_eq!(opts.done_since, None);
+        assert_eq!(opts.created_since, None);
+        assert_eq!(opts.modified_since, None);
     }
 
     #[test]
-    fn test_parse_list_sub_ids() {
-        let res = Cli::parse_id_list("3.3,2.3,1.2,3.3");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1.2,2.3,3.3".to_string());
+    fn test_print_filter_options() {
+        let cli = get_cli("tsk print --pending --tag=backend --match=fix -i --or --top-level-only");
+        assert!(cli.is_ok());
+        let opts = cli.unwrap().get_print_options();
+
+        assert!(opts.pending);
+        assert!(!opts.done);
+        assert!(opts.any);
+        assert!(opts.top_level_only);
+        assert_eq!(opts.tags, ["backend"]);
+        assert!(opts.pattern.unwrap().is_match("Fix it"));
+
+        let cli = get_cli("tsk do 1 --done --match=fix --or");
+        assert!(cli.is_ok());
+        let opts = cli.unwrap().get_print_options();
+        assert!(!opts.done);
+        assert!(!opts.any);
+        assert!(opts.pattern.is_none());
+
+        let cli = get_cli("tsk print --match=");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk print --tag=+1st");
+        assert!(cli.is_err());
     }
 
     #[test]
-    fn test_parse_list_mixed_ids() {
-        let res = Cli::parse_id_list("1.2,2.3,3.3,4,4,3.3,12,5");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1.2,2.3,3.3,4,5,12".to_string());
+    fn test_recur_cmd() {
+        let cli = get_cli("tsk recur 2 every 3 days");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert_eq!(get_ids(&cli), "2");
+        assert!(matches!(
+            cli.command,
+            Command::Recur { recurrence: Some(r), .. } if r.to_string() == "every 3 days"
+        ));
+
+        let cli = get_cli("tsk recur -a week");
+        assert!(cli.is_ok());
+        assert!(matches!(
+            cli.unwrap().command,
+            Command::Recur { recurrence: Some(r), .. } if r.to_string() == "every week"
+        ));
+
+        let cli = get_cli("tsk recur 1 none");
+        assert!(cli.is_ok());
+        assert!(matches!(
+            cli.unwrap().command,
+            Command::Recur {
+                recurrence: None,
+                ..
+            }
+        ));
+
+        let cli = get_cli("tsk add --every=1st test");
+        assert!(cli.is_ok());
+        assert_eq!(cli.unwrap().recurrence.unwrap().to_string(), "every 1st");
+
+        let cli = get_cli("tsk recur 1");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk recur 1 sometimes");
+        assert!(cli.is_err());
     }
 
     #[test]
-    fn test_parse_range_normal_ids() {
-        let res = Cli::parse_id_range("1..5");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1,2,3,4,5".to_string());
+    fn test_sort_cmd() {
+        let cli = get_cli("tsk sort --by=priority");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
 
-        let res = Cli::parse_id_range("5..1");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1,2,3,4,5".to_string());
+        assert!(matches!(cli.command, Command::Sort(SortKey::Priority)));
+
+        let cli = get_cli("tsk sort");
+        assert!(cli.is_err());
     }
 
     #[test]
-    fn test_parse_range_sub_ids() {
-        let res = Cli::parse_id_range("1.2..5.9");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1.2,2,3,4,5.9".to_string());
+    fn test_journal_cmds() {
+        let cli = get_cli("tsk revert");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert!(matches!(cli.command, Command::Revert(1)));
+
+        let cli = get_cli("tsk redo 3");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert!(matches!(cli.command, Command::Redo(3)));
 
-        let res = Cli::parse_id_range("5.9..1.2");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1.2,2,3,4,5.9".to_string());
+        let cli = get_cli("tsk history");
+        assert!(cli.is_ok());
+        assert!(matches!(cli.unwrap().command, Command::History));
+
+        let cli = get_cli("tsk revert 0");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk redo a");
+        assert!(cli.is_err());
     }
 
     #[test]
-    fn test_parse_range_mixed_ids() {
-        let res = Cli::parse_id_range("1..5.9");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1,2,3,4,5.9".to_string());
+    fn test_backup_cmds() {
+        let cli = get_cli("tsk backups");
+        assert!(cli.is_ok());
+        assert!(matches!(cli.unwrap().command, Command::Backups));
 
-        let res = Cli::parse_id_range("5.9..1");
-        let res = res.unwrap_or_default();
-        assert_eq!(res, "1,2,3,4,5.9".to_string());
+        let cli = get_cli("tsk restore 2");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert!(matches!(cli.command, Command::Restore(2)));
+
+        let cli = get_cli("tsk restore");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk restore 0");
+        assert!(cli.is_err());
+
+        let cli = get_cli(&format!("tsk restore {}", MAX_BACKUPS + 1));
+        assert!(cli.is_err());
+    }
+
+    /// The pattern parsed for `find`.
+    fn get_pattern(cli: Cli) -> Regex {
+        match cli.command {
+            Command::Find(pattern) => pattern,
+            c => panic!("Expected `find`, got {c:?}"),
+        }
+    }
+
+    #[test]
+    fn test_find_cmd() {
+        let cli = get_cli("tsk find fix the");
+        assert!(cli.is_ok());
+        let pattern = get_pattern(cli.unwrap());
+
+        assert!(pattern.is_match("fix the docs"));
+        assert!(!pattern.is_match("Fix the docs"));
+
+        let cli = get_cli("tsk find -i fix.");
+        assert!(cli.is_ok());
+        let pattern = get_pattern(cli.unwrap());
+
+        assert!(pattern.is_match("Fix. docs"));
+        assert!(!pattern.is_match("fixed docs"));
+
+        let cli = get_cli("tsk find --regex ^fix(ed)?$");
+        assert!(cli.is_ok());
+        let pattern = get_pattern(cli.unwrap());
+
+        assert!(pattern.is_match("fixed"));
+        assert!(!pattern.is_match("unfixed"));
+
+        let cli = get_cli("tsk find --regex fix(");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk find");
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_archive_cmds() {
+        let cli = get_cli("tsk archive");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert!(matches!(&cli.command, Command::Archive(s) if s.is_empty()));
+
+        let cli = get_cli("tsk archive release notes");
+        assert!(cli.is_ok());
+        assert!(matches!(cli.unwrap().command, Command::Archive(s) if s == "release notes"));
+
+        let cli = get_cli("tsk unarchive 3");
+        assert!(cli.is_ok());
+        let cli = cli.unwrap();
+
+        assert!(matches!(cli.command, Command::Unarchive(3)));
+
+        let cli = get_cli("tsk unarchive");
+        assert!(cli.is_err());
+
+        let cli = get_cli("tsk unarchive 0");
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_unknow_command() {
+        let cli = get_cli("tsk bob 50");
+        assert!(cli.is_err())
     }
 }
@@ -0,0 +1,413 @@
+use chrono::{Datelike, Days, Local, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::error::Error;
+
+const WEEKDAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "monday"),
+    (Weekday::Tue, "tuesday"),
+    (Weekday::Wed, "wednesday"),
+    (Weekday::Thu, "thursday"),
+    (Weekday::Fri, "friday"),
+    (Weekday::Sat, "saturday"),
+    (Weekday::Sun, "sunday"),
+];
+
+/// When a recurring task comes back after being done, ex.: `every 3 days`, `every fri`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Recurrence {
+    /// Every `n` days, weeks, months or years.
+    Every(u32, Unit),
+
+    /// Every given day of the week.
+    Weekday(Weekday),
+
+    /// Every given day of the month, the last day for months that are shorter.
+    MonthDay(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// Today's date in the local timezone.
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Parse a date relative to `today`.
+/// Accepts `2026-11-03`, `today`, `tomorrow`, `yesterday`, a weekday (`fri`, `friday`),
+/// which is its next occurrence counting today, and offsets like `+3d`, `+2w`, `+1m`, `+1y`
+/// or `-3d` for the past.
+pub fn parse_date(date: &str, today: NaiveDate) -> Result<NaiveDate, Error> {
+    parse(date, today, false)
+}
+
+/// Same as `parse_date`, except that a weekday is its last occurrence counting today.
+/// Used for dates that are in the past, ex.: `--done-since=monday`.
+pub fn parse_past_date(date: &str, today: NaiveDate) -> Result<NaiveDate, Error> {
+    parse(date, today, true)
+}
+
+fn parse(date: &str, today: NaiveDate, past: bool) -> Result<NaiveDate, Error> {
+    let date = date.trim().to_lowercase();
+    let invalid = || Error::InvalidOption(format!("Invalid date `{date}`"));
+
+    match date.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today + Days::new(1)),
+        "yesterday" => return Ok(today - Days::new(1)),
+        _ => (),
+    }
+
+    if let Ok(d) = NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+        return Ok(d);
+    }
+
+    if let Some(weekday) = parse_weekday(&date) {
+        let (from, to) = if past {
+            (weekday, today.weekday())
+        } else {
+            (today.weekday(), weekday)
+        };
+        let days = (7 + to.num_days_from_monday() - from.num_days_from_monday()) % 7;
+        let days = Days::new(days.into());
+        return Ok(if past { today - days } else { today + days });
+    }
+
+    let (offset, forward) = match (date.strip_prefix('+'), date.strip_prefix('-')) {
+        (Some(o), _) => (o, true),
+        (_, Some(o)) => (o, false),
+        _ => return Err(invalid()),
+    };
+    if offset.len() < 2 {
+        return Err(invalid());
+    }
+    let (n, unit) = offset.split_at(offset.len() - 1);
+    let n = n.parse::<u32>().map_err(|_| invalid())?;
+
+    let (days, months) = match unit {
+        "d" => (Some(u64::from(n)), None),
+        "w" => (Some(u64::from(n) * 7), None),
+        "m" => (None, Some(n)),
+        "y" => (None, n.checked_mul(12)),
+        _ => return Err(invalid()),
+    };
+    match (days, months, forward) {
+        (Some(d), _, true) => today.checked_add_days(Days::new(d)),
+        (Some(d), _, false) => today.checked_sub_days(Days::new(d)),
+        (_, Some(m), true) => today.checked_add_months(Months::new(m)),
+        (_, Some(m), false) => today.checked_sub_months(Months::new(m)),
+        _ => None,
+    }
+    .ok_or_else(invalid)
+}
+
+/// Format a number of seconds as hours and minutes, ex.: `1h 05m`, `12m`.
+pub fn format_duration(secs: i64) -> String {
+    let mins = secs.max(0) / 60;
+    match (mins / 60, mins % 60) {
+        (0, m) => format!("{m}m"),
+        (h, m) => format!("{h}h {m:02}m"),
+    }
+}
+
+/// Parse the full or abbreviated name of a weekday, ex.: `fri`, `friday`.
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    if day.len() < 3 {
+        return None;
+    }
+    WEEKDAYS
+        .iter()
+        .find(|(_, name)| name.starts_with(day))
+        .map(|(d, _)| *d)
+}
+
+impl Recurrence {
+    /// The next date the task is due on, after `today`.
+    /// Intervals are counted from `due`, skipping the occurrences that were missed.
+    pub fn next(&self, due: Option<NaiveDate>, today: NaiveDate) -> NaiveDate {
+        match *self {
+            Recurrence::Every(n, unit) => {
+                let step = |d: NaiveDate| match unit {
+                    Unit::Day => d.checked_add_days(Days::new(n.into())),
+                    Unit::Week => d.checked_add_days(Days::new(u64::from(n) * 7)),
+                    Unit::Month => d.checked_add_months(Months::new(n)),
+                    Unit::Year => d.checked_add_months(Months::new(n.saturating_mul(12))),
+                };
+                let mut next = step(due.unwrap_or(today)).unwrap_or(NaiveDate::MAX);
+                while next <= today {
+                    next = step(next).unwrap_or(NaiveDate::MAX);
+                }
+                next
+            }
+            Recurrence::Weekday(w) => {
+                let days =
+                    (7 + w.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+                let days = if days == 0 { 7 } else { days };
+                today + Days::new(days.into())
+            }
+            Recurrence::MonthDay(day) => {
+                let this_month = today.with_day(1).expect("every month has a 1st");
+                let on_day = |first: NaiveDate| {
+                    (28..=day)
+                        .rev()
+                        .find_map(|d| first.with_day(d))
+                        .or_else(|| first.with_day(day))
+                        .expect("day is between 1 and 31")
+                };
+                let next = on_day(this_month);
+                if next > today {
+                    next
+                } else {
+                    on_day(this_month + Months::new(1))
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = Error;
+
+    /// Accepts `every day`, `every 3 weeks`, `every month`, `every fri` or `every 15th`,
+    /// the leading `every` is optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim().to_lowercase();
+        let rule = rule.strip_prefix("every").unwrap_or(&rule).trim();
+        let invalid = || Error::InvalidOption(format!("Invalid recurrence `{s}`"));
+
+        let unit = |u: &str| match u.trim_end_matches('s') {
+            "day" => Some(Unit::Day),
+            "week" => Some(Unit::Week),
+            "month" => Some(Unit::Month),
+            "year" => Some(Unit::Year),
+            _ => None,
+        };
+
+        if let Some(u) = unit(rule) {
+            return Ok(Recurrence::Every(1, u));
+        }
+        if let Some(w) = parse_weekday(rule) {
+            return Ok(Recurrence::Weekday(w));
+        }
+
+        if let Some((n, u)) = rule.split_once(' ') {
+            let n = n.parse::<u32>().map_err(|_| invalid())?;
+            let u = unit(u.trim()).ok_or_else(invalid)?;
+            if n == 0 {
+                return Err(invalid());
+            }
+            return Ok(Recurrence::Every(n, u));
+        }
+
+        let day = ["st", "nd", "rd", "th"]
+            .iter()
+            .find_map(|suffix| rule.strip_suffix(suffix))
+            .and_then(|d| d.parse::<u32>().ok())
+            .filter(|d| (1..=31).contains(d))
+            .ok_or_else(invalid)?;
+        Ok(Recurrence::MonthDay(day))
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Every(n, unit) => {
+                let unit = match unit {
+                    Unit::Day => "day",
+                    Unit::Week => "week",
+                    Unit::Month => "month",
+                    Unit::Year => "year",
+                };
+                if *n == 1 {
+                    write!(f, "every {unit}")
+                } else {
+                    write!(f, "every {n} {unit}s")
+                }
+            }
+            Recurrence::Weekday(w) => {
+                let name = WEEKDAYS.iter().find(|(d, _)| d == w).map_or("", |(_, n)| n);
+                write!(f, "every {name}")
+            }
+            Recurrence::MonthDay(d) => {
+                let suffix = match (d % 10, d % 100) {
+                    (_, 11..=13) => "th",
+                    (1, _) => "st",
+                    (2, _) => "nd",
+                    (3, _) => "rd",
+                    _ => "th",
+                };
+                write!(f, "every {d}{suffix}")
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(r: Recurrence) -> Self {
+        r.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A thursday.
+    fn get_today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 15).unwrap()
+    }
+
+    fn get_date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_iso_date() {
+        let d = parse_date("2026-11-03", get_today());
+        assert_eq!(d, Ok(get_date(2026, 11, 3)));
+
+        assert!(parse_date("2026-13-03", get_today()).is_err());
+        assert!(parse_date("03/11/2026", get_today()).is_err());
+    }
+
+    #[test]
+    fn test_parse_relative_days() {
+        assert_eq!(parse_date("today", get_today()), Ok(get_today()));
+        assert_eq!(
+            parse_date("Tomorrow", get_today()),
+            Ok(get_date(2026, 10, 16))
+        );
+        assert_eq!(
+            parse_date("yesterday", get_today()),
+            Ok(get_date(2026, 10, 14))
+        );
+    }
+
+    #[test]
+    fn test_parse_weekday() {
+        assert_eq!(parse_date("fri", get_today()), Ok(get_date(2026, 10, 16)));
+        assert_eq!(
+            parse_date("monday", get_today()),
+            Ok(get_date(2026, 10, 19))
+        );
+        assert_eq!(parse_date("thu", get_today()), Ok(get_today()));
+
+        assert!(matches!(
+            parse_date("fr", get_today()),
+            Err(Error::InvalidOption(_))
+        ));
+        assert!(parse_date("fridays", get_today()).is_err());
+    }
+
+    #[test]
+    fn test_parse_past_weekday() {
+        let d = parse_past_date("mon", get_today());
+        assert_eq!(d, Ok(get_date(2026, 10, 12)));
+        assert_eq!(parse_past_date("thu", get_today()), Ok(get_today()));
+        assert_eq!(
+            parse_past_date("fri", get_today()),
+            Ok(get_date(2026, 10, 9))
+        );
+        assert_eq!(
+            parse_past_date("yesterday", get_today()),
+            Ok(get_date(2026, 10, 14))
+        );
+    }
+
+    #[test]
+    fn test_parse_recurrence() {
+        let parse = |r: &str| r.parse::<Recurrence>();
+        assert_eq!(parse("every week"), Ok(Recurrence::Every(1, Unit::Week)));
+        assert_eq!(parse("every 3 days"), Ok(Recurrence::Every(3, Unit::Day)));
+        assert_eq!(parse("month"), Ok(Recurrence::Every(1, Unit::Month)));
+        assert_eq!(parse("every Fri"), Ok(Recurrence::Weekday(Weekday::Fri)));
+        assert_eq!(parse("every 1st"), Ok(Recurrence::MonthDay(1)));
+        assert_eq!(parse("every 22nd"), Ok(Recurrence::MonthDay(22)));
+
+        assert!(matches!(
+            parse("every 0 days"),
+            Err(Error::InvalidOption(_))
+        ));
+        assert!(parse("every 32nd").is_err());
+        assert!(parse("every now and then").is_err());
+        assert!(parse("").is_err());
+
+        for r in [
+            "every day",
+            "every 2 weeks",
+            "every friday",
+            "every 3rd",
+            "every 11th",
+        ] {
+            assert_eq!(parse(r).unwrap().to_string(), r);
+        }
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let next = |r: &str, due: Option<NaiveDate>| {
+            r.parse::<Recurrence>().unwrap().next(due, get_today())
+        };
+
+        assert_eq!(next("every week", None), get_date(2026, 10, 22));
+        // Missed occurrences are skipped.
+        assert_eq!(
+            next("every week", Some(get_date(2026, 10, 1))),
+            get_date(2026, 10, 22)
+        );
+        assert_eq!(
+            next("every 3 days", Some(get_date(2026, 10, 15))),
+            get_date(2026, 10, 18)
+        );
+
+        assert_eq!(next("every thu", Some(get_today())), get_date(2026, 10, 22));
+        assert_eq!(next("every mon", None), get_date(2026, 10, 19));
+
+        assert_eq!(next("every 15th", None), get_date(2026, 11, 15));
+        assert_eq!(next("every 20th", None), get_date(2026, 10, 20));
+        assert_eq!(
+            next("every 31st", Some(get_date(2026, 10, 31))),
+            get_date(2026, 10, 31)
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(59), "0m");
+        assert_eq!(format_duration(12 * 60), "12m");
+        assert_eq!(format_duration(65 * 60), "1h 05m");
+        assert_eq!(format_duration(-5), "0m");
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_date("+3d", get_today()), Ok(get_date(2026, 10, 18)));
+        assert_eq!(parse_date("+2w", get_today()), Ok(get_date(2026, 10, 29)));
+        assert_eq!(parse_date("+1m", get_today()), Ok(get_date(2026, 11, 15)));
+        assert_eq!(parse_date("+1y", get_today()), Ok(get_date(2027, 10, 15)));
+
+        assert_eq!(parse_date("-3d", get_today()), Ok(get_date(2026, 10, 12)));
+        assert_eq!(parse_date("-1m", get_today()), Ok(get_date(2026, 9, 15)));
+
+        assert!(parse_date("+d", get_today()).is_err());
+        assert!(parse_date("+3x", get_today()).is_err());
+        assert!(parse_date("3d", get_today()).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! The errors returned by tsk, each kind exits with its own code.
+
+use std::{fmt, io};
+
+use serde_json::error::Category;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A command can't be done as things are, ex.: `stop` when no task is being timed.
+    /// Exit code 1.
+    Command(String),
+    /// An option or argument has an invalid value, or is missing. Exit code 2.
+    InvalidOption(String),
+    /// An id is malformed, ex.: `1..2..3`, or can't be used by the command. Exit code 3.
+    InvalidId(String),
+    /// An id is well formed but no task has it. Exit code 4.
+    OutOfBounds(String),
+    /// Reading or writing a file failed. Exit code 5.
+    Io { context: String, source: io::Error },
+    /// The task file, a backup or the journal isn't valid json. Exit code 6.
+    Corrupt {
+        msg: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+impl Error {
+    /// The code tsk exits with when it fails with this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Command(_) => 1,
+            Self::InvalidOption(_) => 2,
+            Self::InvalidId(_) => 3,
+            Self::OutOfBounds(_) => 4,
+            Self::Io { .. } => 5,
+            Self::Corrupt { .. } => 6,
+        }
+    }
+
+    /// What kind of error it is, printed before the message.
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            Self::Command(_) => "Error",
+            Self::InvalidOption(_) => "Option error",
+            Self::InvalidId(_) | Self::OutOfBounds(_) => "Id error",
+            Self::Io { .. } => "File error",
+            Self::Corrupt { .. } => "Corrupt file",
+        }
+    }
+
+    /// An `Io` error, `context` says what was being done when `source` happened.
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Put `context` on the line before the message, keeping the kind of error.
+    pub fn with_context(mut self, context: &str) -> Self {
+        match &mut self {
+            Self::Command(msg)
+            | Self::InvalidOption(msg)
+            | Self::InvalidId(msg)
+            | Self::OutOfBounds(msg)
+            | Self::Io { context: msg, .. }
+            | Self::Corrupt { msg, .. } => *msg = format!("{context}\n{msg}"),
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Command(msg)
+            | Self::InvalidOption(msg)
+            | Self::InvalidId(msg)
+            | Self::OutOfBounds(msg)
+            | Self::Corrupt { msg, .. } => write!(f, "{msg}"),
+            Self::Io { context, source } => write!(f, "{context}\nErr: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// `Io` errors are equal when they happened doing the same thing, with the same kind of
+/// error, as `io::Error` can't be compared.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Command(a), Self::Command(b))
+            | (Self::InvalidOption(a), Self::InvalidOption(b))
+            | (Self::InvalidId(a), Self::InvalidId(b))
+            | (Self::OutOfBounds(a), Self::OutOfBounds(b)) => a == b,
+            (
+                Self::Io {
+                    context: a,
+                    source: e,
+                },
+                Self::Io {
+                    context: b,
+                    source: f,
+                },
+            ) => a == b && e.kind() == f.kind(),
+            (
+                Self::Corrupt {
+                    msg: a,
+                    line: l,
+                    column: c,
+                },
+                Self::Corrupt {
+                    msg: b,
+                    line: m,
+                    column: d,
+                },
+            ) => a == b && l == m && c == d,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        match e.classify() {
+            Category::Io => Self::io("Unable to read json", e.into()),
+            _ => Self::Corrupt {
+                msg: format!("Unable to deserialize json string\nErr: {e}"),
+                line: e.line(),
+                column: e.column(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_errors_are_corrupt() {
+        let e: Error = serde_json::from_str::<Vec<u8>>("[1,\n  x]")
+            .unwrap_err()
+            .into();
+        assert!(matches!(
+            e,
+            Error::Corrupt {
+                line: 2,
+                column: 3,
+                ..
+            }
+        ));
+        assert_eq!(e.exit_code(), 6);
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            Error::Command(String::new()),
+            Error::InvalidOption(String::new()),
+            Error::InvalidId(String::new()),
+            Error::OutOfBounds(String::new()),
+            Error::io("", io::ErrorKind::Other.into()),
+            Error::Corrupt {
+                msg: String::new(),
+                line: 0,
+                column: 0,
+            },
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(Error::exit_code).collect();
+        codes.dedup();
+        assert_eq!(codes, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_context_keeps_the_kind() {
+        let e = Error::OutOfBounds("Id `9` is out of bounds".to_owned()).with_context("Moving 9");
+        assert_eq!(e.to_string(), "Moving 9\nId `9` is out of bounds");
+        assert_eq!(e.exit_code(), 4);
+    }
+
+    #[test]
+    fn test_io_errors_keep_their_source() {
+        use std::error::Error as _;
+
+        let source = io::Error::new(io::ErrorKind::NotFound, "no such file");
+        let e = Error::io("Unable to read 'tasks' file", source).with_context("Loading");
+        assert_eq!(
+            e.to_string(),
+            "Loading\nUnable to read 'tasks' file\nErr: no such file"
+        );
+        let source = e.source().and_then(|s| s.downcast_ref::<io::Error>());
+        assert_eq!(source.map(io::Error::kind), Some(io::ErrorKind::NotFound));
+    }
+}
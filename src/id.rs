@@ -0,0 +1,530 @@
+//! Parsing of the ids that select tasks: single ids like `2.1`, lists like `1,3.2`,
+//! ranges like `2..5`, which can be mixed as in `1,3..5,7.2`, and `all`. Levels of an id
+//! can count from the end, `-1` or `last` being the last task and `3.-2` the second to
+//! last subtask of 3, and `3.*` selects every subtask of 3. Tasks can also be referred to
+//! by their unique id, `@<uid>`, by a tag, `+<tag>`, or by being `done` or `pending`.
+
+use std::{fmt, str::FromStr};
+
+use crate::{error::Error, task_file::TaskFile};
+
+/// The position of a task, ex.: `2.1` is the first subtask of the second task.
+/// Holds the zero based index of each level, so it's never empty.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(Vec<usize>);
+
+/// One level of a `Target::Path`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Zero based index from the start.
+    Nth(usize),
+    /// One based index from the end, `1` is the last task.
+    FromEnd(usize),
+    /// Every task at this level.
+    Every,
+}
+
+/// What an id refers to: a task by its position or its unique id, or every task with a
+/// tag or state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Id(TaskId),
+    /// A position that depends on the tasks there are, ex.: `-1`, `last` or `3.*`.
+    Path(Vec<Step>),
+    /// Start of a task's unique id.
+    Uid(String),
+    Tag(String),
+    Done,
+    Pending,
+}
+
+/// A single target or a range of tasks, ids in a list are made of these.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Part {
+    Single(Target),
+    /// Tasks from one end to the other, in any order, among the subtasks of the closest
+    /// parent both ends share. Either end keeps its subids, ex.: `2.1..2.3` selects `2.1`,
+    /// `2.2` and `2.3`, `1.2..4` selects `1.2`, `2`, `3` and `4`.
+    Range(Target, Target),
+}
+
+/// The tasks a command works on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// Comma separated parts, never empty.
+    List(Vec<Part>),
+    /// Every top level task.
+    All,
+}
+
+impl TaskId {
+    /// The top level task at the zero based `index`.
+    pub fn top(index: usize) -> Self {
+        Self(vec![index])
+    }
+
+    /// The subtask at the zero based `index` of this task.
+    pub fn child(&self, index: usize) -> Self {
+        let mut path = self.0.clone();
+        path.push(index);
+        Self(path)
+    }
+
+    /// The zero based index of each level, the top level first.
+    pub fn get_path(&self) -> &[usize] {
+        &self.0
+    }
+
+    /// The zero based index of the task among its siblings.
+    pub fn get_index(&self) -> usize {
+        *self.0.last().expect("ids aren't empty")
+    }
+
+    /// The task this is a subtask of, `None` for top level tasks.
+    pub fn get_parent(&self) -> Option<Self> {
+        let (_, parent) = self.0.split_last().expect("ids aren't empty");
+        (!parent.is_empty()).then(|| Self(parent.to_vec()))
+    }
+
+    pub fn is_top_level(&self) -> bool {
+        self.0.len() == 1
+    }
+
+    /// Whether this is a subtask of `other`, at any depth.
+    pub fn is_inside(&self, other: &Self) -> bool {
+        self.0.len() > other.0.len() && self.0.starts_with(&other.0)
+    }
+}
+
+impl FromStr for TaskId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidId(format!("Invalid id `{s}`"));
+        let path = s
+            .split('.')
+            .map(|i| match parse_index(i) {
+                Some(i) => Ok(i - 1),
+                None => Err(invalid()),
+            })
+            .collect::<Result<Vec<usize>, Error>>()?;
+        Ok(Self(path))
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let levels: Vec<String> = self.0.iter().map(|i| (i + 1).to_string()).collect();
+        write!(f, "{}", levels.join("."))
+    }
+}
+
+/// Parse a one based index, only made of digits.
+fn parse_index(s: &str) -> Option<usize> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok().filter(|i| *i > 0)
+}
+
+impl FromStr for Step {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let step = match s {
+            "*" => Some(Self::Every),
+            "last" => Some(Self::FromEnd(1)),
+            _ => match s.strip_prefix('-') {
+                Some(i) => parse_index(i).map(Self::FromEnd),
+                None => parse_index(s).map(|i| Self::Nth(i - 1)),
+            },
+        };
+        step.ok_or_else(|| Error::InvalidId(format!("Invalid id level `{s}`")))
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nth(i) => write!(f, "{}", i + 1),
+            Self::FromEnd(i) => write!(f, "-{i}"),
+            Self::Every => write!(f, "*"),
+        }
+    }
+}
+
+impl Target {
+    /// Whether the target can only refer to one task, as the ends of ranges must.
+    pub fn is_single(&self) -> bool {
+        match self {
+            Self::Id(_) | Self::Uid(_) => true,
+            Self::Path(steps) => !steps.contains(&Step::Every),
+            Self::Tag(_) | Self::Done | Self::Pending => false,
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(uid) = s.strip_prefix('@') {
+            if uid.is_empty() {
+                return Err(Error::InvalidId("Missing unique id after `@`".to_owned()));
+            }
+            return Ok(Self::Uid(uid.to_owned()));
+        } else if let Some(tag) = s.strip_prefix('+') {
+            if !TaskFile::is_valid_tag(tag) {
+                return Err(Error::InvalidId(format!("Invalid tag `{s}`")));
+            }
+            return Ok(Self::Tag(tag.to_owned()));
+        }
+        match s {
+            "done" => return Ok(Self::Done),
+            "pending" => return Ok(Self::Pending),
+            _ => (),
+        }
+
+        let steps = s
+            .split('.')
+            .map(|i| {
+                i.parse()
+                    .map_err(|_| Error::InvalidId(format!("Invalid id `{s}`")))
+            })
+            .collect::<Result<Vec<Step>, Error>>()?;
+        let path: Option<Vec<usize>> = steps
+            .iter()
+            .map(|s| match s {
+                Step::Nth(i) => Some(*i),
+                _ => None,
+            })
+            .collect();
+        match path {
+            Some(path) => Ok(Self::Id(TaskId(path))),
+            None => Ok(Self::Path(steps)),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "{id}"),
+            Self::Path(steps) => {
+                let steps: Vec<String> = steps.iter().map(|s| s.to_string()).collect();
+                write!(f, "{}", steps.join("."))
+            }
+            Self::Uid(uid) => write!(f, "@{uid}"),
+            Self::Tag(tag) => write!(f, "+{tag}"),
+            Self::Done => write!(f, "done"),
+            Self::Pending => write!(f, "pending"),
+        }
+    }
+}
+
+impl Part {
+    /// Turn the part into the ids it selects, `resolve` returns the ids a target refers to.
+    fn expand(
+        &self,
+        resolve: &impl Fn(&Target) -> Result<Vec<TaskId>, Error>,
+    ) -> Result<Vec<TaskId>, Error> {
+        let (start, end) = match self {
+            Self::Single(t) => return resolve(t),
+            Self::Range(start, end) => (start, end),
+        };
+        let single = |t| match resolve(t)?.as_slice() {
+            [id] => Ok(id.clone()),
+            _ => Err(Error::InvalidId(format!("`{t}` can't be used in a range"))),
+        };
+        let (mut start, mut end) = (single(start)?, single(end)?);
+        // Allow for 5..8 and 8..5
+        if start > end {
+            (start, end) = (end, start);
+        }
+
+        // The level where the ends stop sharing parents, ex.: 1 for `2.1..2.3`.
+        let depth = start
+            .0
+            .iter()
+            .zip(end.0.iter())
+            .take_while(|(s, e)| s == e)
+            .count();
+        if depth == start.0.len() || depth == end.0.len() {
+            // One end is inside the other, ex.: `2..2.3`.
+            return Ok(vec![start, end]);
+        }
+
+        let parent = &start.0[..depth];
+        let (first, last) = (start.0[depth], end.0[depth]);
+        let mut ids: Vec<TaskId> = (first..=last)
+            .map(|i| {
+                let mut path = parent.to_vec();
+                path.push(i);
+                TaskId(path)
+            })
+            .collect();
+        // The ends keep their subids.
+        ids[0] = start;
+        *ids.last_mut().expect("ranges have two ends") = end;
+        Ok(ids)
+    }
+}
+
+impl FromStr for Part {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((start, end)) = s.split_once("..") else {
+            return Ok(Self::Single(s.parse()?));
+        };
+        if end.contains("..") {
+            return Err(Error::InvalidId(
+                "Range pattern can only be used once".to_owned(),
+            ));
+        }
+
+        let (start, end): (Target, Target) = (start.parse()?, end.parse()?);
+        for t in [&start, &end] {
+            if !t.is_single() {
+                return Err(Error::InvalidId(format!("`{t}` can't be used in ranges")));
+            }
+        }
+        Ok(Self::Range(start, end))
+    }
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Single(t) => write!(f, "{t}"),
+            Self::Range(start, end) => write!(f, "{start}..{end}"),
+        }
+    }
+}
+
+impl Selection {
+    /// The target of a selection of one id, `None` for lists, ranges and `all`.
+    pub fn as_single(&self) -> Option<&Target> {
+        match self {
+            Self::List(parts) => match parts.as_slice() {
+                [Part::Single(t)] => Some(t),
+                _ => None,
+            },
+            Self::All => None,
+        }
+    }
+
+    /// Turn the selection into the ids it selects, sorted with parents before their
+    /// subtasks. `resolve` returns the ids a target refers to, and `task_count` is how
+    /// many top level tasks `all` selects.
+    pub fn expand(
+        &self,
+        resolve: impl Fn(&Target) -> Result<Vec<TaskId>, Error>,
+        task_count: usize,
+    ) -> Result<Vec<TaskId>, Error> {
+        let mut ids = match self {
+            Self::All => (0..task_count).map(TaskId::top).collect(),
+            Self::List(parts) => {
+                let mut ids = Vec::new();
+                for p in parts {
+                    ids.extend(p.expand(&resolve)?);
+                }
+                ids
+            }
+        };
+
+        // Subtasks go after their parent task, ex.: 1,1.2,1.10,2
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+}
+
+impl FromStr for Selection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(Error::InvalidId("No id provided".to_owned()));
+        }
+        if s == "all" {
+            return Ok(Self::All);
+        }
+
+        if s.split(',').any(str::is_empty) {
+            return Err(Error::InvalidId(
+                "Id list contains invalid patterns".to_owned(),
+            ));
+        }
+        let parts = s.split(',').map(str::parse).collect::<Result<_, _>>()?;
+        Ok(Self::List(parts))
+    }
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::List(parts) => {
+                let parts: Vec<String> = parts.iter().map(|p| p.to_string()).collect();
+                write!(f, "{}", parts.join(","))
+            }
+            Self::All => write!(f, "all"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Expand `ids`, which may only hold positional ids, into a list like `1,2.1`.
+    fn expand(ids: &str, task_count: usize) -> String {
+        let sel: Selection = ids.parse().unwrap();
+        let resolve = |t: &Target| match t {
+            Target::Id(id) => Ok(vec![id.clone()]),
+            _ => unreachable!(),
+        };
+        let ids: Vec<String> = sel
+            .expand(resolve, task_count)
+            .unwrap()
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        ids.join(",")
+    }
+
+    #[test]
+    fn test_parse_task_id() {
+        let id: TaskId = "2.10.1".parse().unwrap();
+        assert_eq!(id.get_path(), [1, 9, 0]);
+        assert_eq!(id.to_string(), "2.10.1");
+        assert_eq!(id.get_parent(), Some("2.10".parse().unwrap()));
+        assert!(!id.is_top_level());
+        assert!(id.is_inside(&TaskId::top(1)));
+        assert!(!id.is_inside(&id));
+
+        for invalid in ["", "0", "1.0", "a", "1.", ".1", "+1", "1..2", "-1"] {
+            assert!(invalid.parse::<TaskId>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!("all".parse::<Selection>().unwrap(), Selection::All);
+        assert_eq!(
+            "@ab".parse::<Selection>().unwrap().as_single(),
+            Some(&Target::Uid("ab".to_owned()))
+        );
+        assert_eq!(
+            "1,+home".parse::<Selection>().unwrap().to_string(),
+            "1,+home"
+        );
+        assert_eq!(
+            "1,3..5,7.2".parse::<Selection>().unwrap().to_string(),
+            "1,3..5,7.2"
+        );
+        assert!(matches!(
+            "@a..2".parse::<Selection>().unwrap(),
+            Selection::List(p) if matches!(p[..], [Part::Range(Target::Uid(_), Target::Id(_))])
+        ));
+        assert_eq!(
+            "done,pending".parse::<Selection>().unwrap(),
+            Selection::List(vec![
+                Part::Single(Target::Done),
+                Part::Single(Target::Pending)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let path = |s: &str| match s.parse::<Target>().unwrap() {
+            Target::Path(steps) => steps,
+            t => panic!("`{s}` parsed as {t:?}"),
+        };
+        assert_eq!(path("-1"), [Step::FromEnd(1)]);
+        assert_eq!(path("last"), [Step::FromEnd(1)]);
+        assert_eq!(path("3.*"), [Step::Nth(2), Step::Every]);
+        assert_eq!(
+            path("2.-3.1"),
+            [Step::Nth(1), Step::FromEnd(3), Step::Nth(0)]
+        );
+        assert_eq!("2.last".parse::<Target>().unwrap().to_string(), "2.-1");
+
+        // Positions that don't depend on the tasks stay plain ids.
+        assert!(matches!("2.1".parse::<Target>().unwrap(), Target::Id(_)));
+        for invalid in ["-0", "--1", "1.-", "*1", "1.**", "lasts"] {
+            assert!(invalid.parse::<Target>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_selection() {
+        for invalid in [
+            "", "1..2..3", "1,,2", "1,", "+home..3", "@", "+1a", "2,a", "1...2", "3.*..5",
+            "done..2", "1,all",
+        ] {
+            let res = invalid.parse::<Selection>();
+            assert!(matches!(res, Err(Error::InvalidId(_))), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_expand_list_normal_ids() {
+        assert_eq!(expand("1,2,3,4,4,3,12,5", 0), "1,2,3,4,5,12");
+    }
+
+    #[test]
+    fn test_expand_list_sub_ids() {
+        assert_eq!(expand("3.3,2.3,1.2,3.3", 0), "1.2,2.3,3.3");
+    }
+
+    #[test]
+    fn test_expand_list_mixed_ids() {
+        assert_eq!(expand("1.2,2.3,3.3,4,4,3.3,12,5", 0), "1.2,2.3,3.3,4,5,12");
+    }
+
+    #[test]
+    fn test_expand_list_nested_ids() {
+        assert_eq!(
+            expand("2,1.10.1,1.2.3,1.2,1.9", 0),
+            "1.2,1.2.3,1.9,1.10.1,2"
+        );
+    }
+
+    #[test]
+    fn test_expand_range_normal_ids() {
+        assert_eq!(expand("1..5", 0), "1,2,3,4,5");
+        assert_eq!(expand("5..1", 0), "1,2,3,4,5");
+    }
+
+    #[test]
+    fn test_expand_range_sub_ids() {
+        assert_eq!(expand("1.2..5.9", 0), "1.2,2,3,4,5.9");
+        assert_eq!(expand("5.9..1.2", 0), "1.2,2,3,4,5.9");
+    }
+
+    #[test]
+    fn test_expand_range_mixed_ids() {
+        assert_eq!(expand("1..5.9", 0), "1,2,3,4,5.9");
+        assert_eq!(expand("5.9..1", 0), "1,2,3,4,5.9");
+    }
+
+    #[test]
+    fn test_expand_range_subtasks() {
+        assert_eq!(expand("1.1..1.4", 0), "1.1,1.2,1.3,1.4");
+        assert_eq!(expand("2.3.4..2.1", 0), "2.1,2.2,2.3.4");
+        assert_eq!(expand("2..2.3", 0), "2,2.3");
+    }
+
+    #[test]
+    fn test_expand_mixed_list() {
+        assert_eq!(expand("7.2,3..5,1", 0), "1,3,4,5,7.2");
+        assert_eq!(expand("1..3,2..4", 0), "1,2,3,4");
+    }
+
+    #[test]
+    fn test_expand_all() {
+        assert_eq!(expand("all", 3), "1,2,3");
+    }
+}
@@ -0,0 +1,537 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    error::Error,
+    task_file::{ArchivedTask, Task},
+};
+
+/// How many operations are kept in the journal.
+const MAX_ENTRIES: usize = 50;
+
+/// How large the journal may get, the oldest operations are dropped past it.
+const MAX_BYTES: usize = 4 * 1024 * 1024;
+
+/// Record of the changes made to a task file, used to revert and redo them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Journal {
+    /// Entries before this position are applied, the ones after it have been reverted.
+    position: usize,
+    entries: Vec<Entry>,
+
+    #[serde(skip)]
+    changed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// What was done, ex.: `do 2,3`.
+    pub operation: String,
+
+    /// Seconds since the unix epoch.
+    pub time: u64,
+
+    /// The tasks before, `None` when they're the tasks after the previous entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    before: Option<Vec<Task>>,
+    after: Vec<Task>,
+
+    /// The archive before and after, only for operations that changed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    archive: Option<(Vec<ArchivedTask>, Vec<ArchivedTask>)>,
+}
+
+impl Journal {
+    /// The journal is kept next to its task file, ex.: `tasks.json.journal`.
+    pub fn get_path(task_file: &Path) -> PathBuf {
+        let mut path = task_file.as_os_str().to_owned();
+        path.push(".journal");
+        PathBuf::from(path)
+    }
+
+    /// Load the journal at `path`, a missing journal is an empty one.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| Error::from(e).with_context("Unable to load the journal")),
+            Err(_) if !path.exists() => Ok(Self::default()),
+            Err(e) => Err(Error::io("Unable to read the journal", e)),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string(self)
+            .map_err(|e| Error::io("Unable to serialize the journal", e.into()))
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Record an operation that changed the tasks from `before` to `after`, and the archive
+    /// when it changed. Operations that were reverted can no longer be redone.
+    pub fn record(
+        &mut self,
+        operation: String,
+        before: Vec<Task>,
+        after: Vec<Task>,
+        archive: Option<(Vec<ArchivedTask>, Vec<ArchivedTask>)>,
+    ) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        self.entries.truncate(self.position);
+        let before = match self.entries.last() {
+            Some(last) if last.after == before => None,
+            _ => Some(before),
+        };
+        self.entries.push(Entry {
+            operation,
+            time,
+            before,
+            after,
+            archive,
+        });
+
+        let mut sizes: Vec<usize> = self.entries.iter().map(Entry::get_size).collect();
+        let mut total: usize = sizes.iter().sum();
+        while self.entries.len() > MAX_ENTRIES || (self.entries.len() > 1 && total > MAX_BYTES) {
+            let first = self.entries.remove(0);
+            total -= sizes.remove(0);
+
+            // The new first entry can't rely on the dropped one for its tasks before.
+            let next = &mut self.entries[0];
+            if next.before.is_none() {
+                next.before = Some(first.after);
+                total -= sizes[0];
+                sizes[0] = next.get_size();
+                total += sizes[0];
+            }
+        }
+        self.position = self.entries.len();
+        self.changed = true;
+    }
+
+    /// The tasks before the entry at `index`.
+    fn get_before(&self, index: usize) -> &[Task] {
+        match &self.entries[index].before {
+            Some(before) => before,
+            None => &self.entries[index - 1].after,
+        }
+    }
+
+    /// Whether `tasks` and `archive` are as the journal left them at the current position.
+    /// They aren't when the task file was changed without being recorded, ex.: restored
+    /// from a backup or edited by hand.
+    pub fn is_current(&self, tasks: &[Task], archive: &[ArchivedTask]) -> bool {
+        let expected = match self.position {
+            0 if self.entries.is_empty() => None,
+            0 => Some(self.get_before(0)),
+            n => Some(self.entries[n - 1].after.as_slice()),
+        };
+        expected.is_none_or(|t| t == tasks) && self.get_archive().is_none_or(|a| a == archive)
+    }
+
+    /// Step back through `steps` operations, returns the tasks as they were before them.
+    pub fn revert(&mut self, steps: usize) -> Result<Vec<Task>, Error> {
+        if steps > self.position {
+            return Err(Error::Command(format!(
+                "Only {} operation(s) can be reverted",
+                self.position
+            )));
+        }
+        self.position -= steps;
+        self.changed = true;
+        Ok(self.get_before(self.position).to_vec())
+    }
+
+    /// Step forward through `steps` reverted operations, returns the tasks as they were after them.
+    pub fn redo(&mut self, steps: usize) -> Result<Vec<Task>, Error> {
+        let available = self.entries.len() - self.position;
+        if steps > available {
+            return Err(Error::Command(format!(
+                "Only {available} operation(s) can be redone"
+            )));
+        }
+        self.position += steps;
+        self.changed = true;
+        Ok(self.entries[self.position - 1].after.clone())
+    }
+
+    /// Returns the archive as it was at the current position, `None` if no recorded
+    /// operation changed it.
+    pub fn get_archive(&self) -> Option<Vec<ArchivedTask>> {
+        let (applied, reverted) = self.entries.split_at(self.position);
+        reverted
+            .iter()
+            .find_map(|e| e.archive.as_ref().map(|(before, _)| before.clone()))
+            .or_else(|| {
+                applied
+                    .iter()
+                    .rev()
+                    .find_map(|e| e.archive.as_ref().map(|(_, after)| after.clone()))
+            })
+    }
+
+    /// Returns the entries, oldest first, along with whether they're applied.
+    pub fn get_entries(&self) -> impl Iterator<Item = (&Entry, bool)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e, i < self.position))
+    }
+
+    /// Summary of what the operation at `index` did to the tasks, ex.: `1 added, 2 done`.
+    pub fn get_effects(&self, index: usize) -> String {
+        self.entries[index].get_effects(self.get_before(index))
+    }
+}
+
+impl Entry {
+    /// Size of the entry once serialized.
+    fn get_size(&self) -> usize {
+        serde_json::to_vec(self).map_or(0, |v| v.len())
+    }
+
+    fn get_effects(&self, before: &[Task]) -> String {
+        let before = Self::flatten(before);
+        let after = Self::flatten(&self.after);
+
+        // Tasks that went in or out of the archive aren't counted as removed or added.
+        let (archive_before, archive_after) = match &self.archive {
+            Some((b, a)) => (Self::flatten_archive(b), Self::flatten_archive(a)),
+            None => Default::default(),
+        };
+        let went_into = |from: &HashMap<&str, Flat>, to: &HashMap<&str, Flat>, uid: &str| {
+            to.contains_key(uid) && !from.contains_key(uid)
+        };
+
+        let (mut added, mut unarchived) = (0, 0);
+        for uid in after.keys().filter(|u| !before.contains_key(*u)) {
+            if went_into(&archive_after, &archive_before, uid) {
+                unarchived += 1;
+            } else {
+                added += 1;
+            }
+        }
+        let (mut removed, mut archived) = (0, 0);
+        for uid in before.keys().filter(|u| !after.contains_key(*u)) {
+            if went_into(&archive_before, &archive_after, uid) {
+                archived += 1;
+            } else {
+                removed += 1;
+            }
+        }
+
+        let (mut edited, mut updated, mut done, mut undone, mut moved_parent) = (0, 0, 0, 0, 0);
+        for (uid, b) in before.iter() {
+            let Some(a) = after.get(uid) else {
+                continue;
+            };
+            if a.task.get_contents() != b.task.get_contents() {
+                edited += 1;
+            }
+            if !a.task.same_details(b.task) {
+                updated += 1;
+            }
+            if a.task.is_done() && !b.task.is_done() {
+                done += 1;
+            } else if !a.task.is_done() && b.task.is_done() {
+                undone += 1;
+            }
+            if a.parent != b.parent {
+                moved_parent += 1;
+            }
+        }
+        let moved = moved_parent + Self::count_reordered(&before, &after);
+
+        let effects: Vec<String> = [
+            (added, "added"),
+            (removed, "removed"),
+            (archived, "archived"),
+            (unarchived, "unarchived"),
+            (edited, "edited"),
+            (updated, "updated"),
+            (done, "done"),
+            (undone, "undone"),
+            (moved, "moved"),
+        ]
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, e)| format!("{n} {e}"))
+        .collect();
+
+        if effects.is_empty() {
+            return "no changes".to_owned();
+        }
+        effects.join(", ")
+    }
+
+    /// Map every task's unique id to the task, its parent's unique id and its position.
+    fn flatten(tasks: &[Task]) -> HashMap<&str, Flat<'_>> {
+        fn collect<'a>(tasks: &'a [Task], parent: &'a str, map: &mut HashMap<&'a str, Flat<'a>>) {
+            for (index, task) in tasks.iter().enumerate() {
+                let flat = Flat {
+                    task,
+                    parent,
+                    index,
+                };
+                map.insert(task.get_uid(), flat);
+                collect(&task.subtasks, task.get_uid(), map);
+            }
+        }
+        let mut map = HashMap::new();
+        collect(tasks, "", &mut map);
+        map
+    }
+
+    fn flatten_archive(archive: &[ArchivedTask]) -> HashMap<&str, Flat<'_>> {
+        archive
+            .iter()
+            .flat_map(|a| Self::flatten(std::slice::from_ref(&a.task)))
+            .collect()
+    }
+
+    /// Count the tasks that changed places among their siblings, without counting the ones
+    /// that only shifted because other tasks were added or removed.
+    fn count_reordered(before: &HashMap<&str, Flat>, after: &HashMap<&str, Flat>) -> usize {
+        // Positions before and after of the tasks that kept the same parent.
+        let mut groups: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+        for (uid, b) in before.iter() {
+            if let Some(a) = after.get(uid).filter(|a| a.parent == b.parent) {
+                groups.entry(b.parent).or_default().push((b.index, a.index));
+            }
+        }
+
+        groups
+            .into_values()
+            .map(|mut g| {
+                g.sort();
+                let after: Vec<usize> = g.iter().map(|(_, a)| *a).collect();
+                g.len() - Self::longest_increasing_len(&after)
+            })
+            .sum()
+    }
+
+    /// Length of the longest increasing subsequence of `v`, the tasks in it kept their order.
+    fn longest_increasing_len(v: &[usize]) -> usize {
+        let mut tails: Vec<usize> = Vec::new();
+        for n in v {
+            let i = tails.partition_point(|t| t < n);
+            if i == tails.len() {
+                tails.push(*n);
+            } else {
+                tails[i] = *n;
+            }
+        }
+        tails.len()
+    }
+}
+
+struct Flat<'a> {
+    task: &'a Task,
+    parent: &'a str,
+    index: usize,
+}
+
+/// Format how long ago `time` was, ex.: `5m ago`.
+pub fn format_ago(time: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let secs = now.saturating_sub(time);
+
+    match secs {
+        0..=59 => "just now".to_owned(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// `tasks` ex.: "a b- c" is tasks with the uids a, b and c, where `-` marks a done task.
+    fn get_tasks(tasks: &str) -> Vec<Task> {
+        let json: Vec<String> = tasks
+            .split(' ')
+            .filter(|t| !t.is_empty())
+            .map(|t| {
+                let uid = t.trim_end_matches('-');
+                let done = t.ends_with('-');
+                format!(r#"{{"uid":"{uid}","contents":"{uid}","done":{done},"subtasks":[]}}"#)
+            })
+            .collect();
+        serde_json::from_str(&format!("[{}]", json.join(","))).unwrap()
+    }
+
+    fn get_effects(before: &str, after: &str) -> String {
+        let mut j = Journal::default();
+        j.record("op".to_owned(), get_tasks(before), get_tasks(after), None);
+        j.get_effects(0)
+    }
+
+    #[test]
+    fn test_revert_and_redo() {
+        let mut j = Journal::default();
+        j.record("add b".to_owned(), get_tasks("a"), get_tasks("a b"), None);
+        j.record(
+            "add c".to_owned(),
+            get_tasks("a b"),
+            get_tasks("a b c"),
+            None,
+        );
+
+        assert_eq!(j.revert(2).unwrap(), get_tasks("a"));
+        assert!(j.revert(1).is_err());
+
+        assert_eq!(j.redo(1).unwrap(), get_tasks("a b"));
+        assert_eq!(j.redo(1).unwrap(), get_tasks("a b c"));
+        assert!(j.redo(1).is_err());
+        assert!(j.is_changed());
+    }
+
+    #[test]
+    fn test_record_drops_reverted_entries() {
+        let mut j = Journal::default();
+        j.record("add b".to_owned(), get_tasks("a"), get_tasks("a b"), None);
+        j.record(
+            "add c".to_owned(),
+            get_tasks("a b"),
+            get_tasks("a b c"),
+            None,
+        );
+        j.revert(1).unwrap();
+        j.record(
+            "add d".to_owned(),
+            get_tasks("a b"),
+            get_tasks("a b d"),
+            None,
+        );
+
+        let ops: Vec<&str> = j.get_entries().map(|(e, _)| e.operation.as_str()).collect();
+        assert_eq!(ops, ["add b", "add d"]);
+        assert!(j.redo(1).is_err());
+    }
+
+    #[test]
+    fn test_journal_is_capped() {
+        let mut j = Journal::default();
+        for i in 0..MAX_ENTRIES + 5 {
+            j.record(i.to_string(), get_tasks("a"), get_tasks("a b"), None);
+        }
+        assert_eq!(j.get_entries().count(), MAX_ENTRIES);
+        assert_eq!(j.get_entries().next().unwrap().0.operation, "5");
+        assert!(j.revert(MAX_ENTRIES).is_ok());
+    }
+
+    #[test]
+    fn test_journal_is_capped_by_size() {
+        let get_task = |i: usize| -> Vec<Task> {
+            let contents = i.to_string().repeat(MAX_BYTES / 8);
+            let json =
+                format!(r#"[{{"uid":"a","contents":"{contents}","done":false,"subtasks":[]}}]"#);
+            serde_json::from_str(&json).unwrap()
+        };
+
+        let mut j = Journal::default();
+        for i in 1..10 {
+            j.record(format!("edit {i}"), get_task(i - 1), get_task(i), None);
+        }
+        assert!(j.to_json().unwrap().len() <= MAX_BYTES);
+        let count = j.get_entries().count();
+        assert!(count < 9);
+
+        // The oldest entry left still reverts to the tasks before it.
+        let oldest = 10 - count;
+        assert_eq!(
+            j.get_entries().next().unwrap().0.operation,
+            format!("edit {oldest}")
+        );
+        assert_eq!(j.revert(count).unwrap(), get_task(oldest - 1));
+    }
+
+    #[test]
+    fn test_unchanged_tasks_before_are_not_stored_twice() {
+        let mut j = Journal::default();
+        j.record("add b".to_owned(), get_tasks("a"), get_tasks("a b"), None);
+        j.record(
+            "add c".to_owned(),
+            get_tasks("a b"),
+            get_tasks("a b c"),
+            None,
+        );
+        // Changed outside of the journal, ex.: restored from a backup.
+        j.record("add e".to_owned(), get_tasks("d"), get_tasks("d e"), None);
+
+        assert!(j.entries[0].before.is_some());
+        assert!(j.entries[1].before.is_none());
+        assert!(j.entries[2].before.is_some());
+        assert_eq!(j.get_effects(1), "1 added");
+        assert_eq!(j.revert(2).unwrap(), get_tasks("a b"));
+    }
+
+    #[test]
+    fn test_effects() {
+        assert_eq!(get_effects("a b", "a b c"), "1 added");
+        assert_eq!(get_effects("a b c", "a"), "2 removed");
+        assert_eq!(get_effects("a b c", "a- b- c"), "2 done");
+        assert_eq!(get_effects("a- b", "a b"), "1 undone");
+        assert_eq!(get_effects("a b c d", "d a b c"), "1 moved");
+        assert_eq!(get_effects("a b c", "c b a"), "2 moved");
+        assert_eq!(get_effects("a b c", "b c"), "1 removed");
+        assert_eq!(get_effects("a", "a"), "no changes");
+    }
+
+    #[test]
+    fn test_updated_effect() {
+        let before = get_tasks("a b");
+        let mut after = before.clone();
+        after[1].set_due(chrono::NaiveDate::from_ymd_opt(2026, 11, 3));
+
+        let mut j = Journal::default();
+        j.record("due 2 2026-11-03".to_owned(), before, after, None);
+        assert_eq!(j.get_effects(0), "1 updated");
+    }
+
+    #[test]
+    fn test_archived_effects() {
+        let archive: Vec<ArchivedTask> = serde_json::from_str(
+            r#"[{"task":{"uid":"b","contents":"b","done":true,"subtasks":[]}}]"#,
+        )
+        .unwrap();
+
+        let mut j = Journal::default();
+        let archived = Some((Vec::new(), archive.clone()));
+        j.record(
+            "clear".to_owned(),
+            get_tasks("a b- c-"),
+            get_tasks("a"),
+            archived,
+        );
+        assert_eq!(j.get_effects(0), "1 removed, 1 archived");
+
+        let unarchived = Some((archive, Vec::new()));
+        j.record(
+            "unarchive 1".to_owned(),
+            get_tasks("a"),
+            get_tasks("a b-"),
+            unarchived,
+        );
+        assert_eq!(j.get_effects(1), "1 unarchived");
+        assert_eq!(j.get_archive(), Some(Vec::new()));
+
+        j.revert(1).unwrap();
+        assert_eq!(j.get_archive().unwrap().len(), 1);
+        j.revert(1).unwrap();
+        assert_eq!(j.get_archive(), Some(Vec::new()));
+    }
+}
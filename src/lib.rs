@@ -0,0 +1,24 @@
+//! Task lists with nested subtasks, kept in json task files.
+//!
+//! Every operation returns a `Result` and nothing is printed, the `tsk` binary is a front end
+//! over this crate. Ex.:
+//! ```no_run
+//! use std::path::Path;
+//! use tsk::{Position, TaskFile};
+//!
+//! let mut tf = TaskFile::load(None, Some(Path::new("tasks.json")))?;
+//! tf.add_task("write the docs", Position::Bottom, None)?;
+//! tf.mark_tasks(&"1".parse()?, true)?;
+//! tf.save()?;
+//! # Ok::<(), tsk::Error>(())
+//! ```
+
+pub mod date;
+pub mod error;
+pub mod id;
+pub mod journal;
+pub mod task_file;
+
+pub use error::Error;
+pub use id::{Selection, Target, TaskId};
+pub use task_file::{Position, PrintOptions, Priority, SortKey, SubTask, Task, TaskFile};
@@ -1,174 +1,354 @@
-use cli::Cli;
-use std::process;
-use task_file::TaskFile;
+use cli::{Cli, Command, ListAction};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    hash::{BuildHasher, RandomState},
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    process,
+    time::SystemTime,
+};
+use tsk::{
+    id::{Target, TaskId},
+    task_file::{TaskFile, DEFAULT_LIST},
+    Error,
+};
 
-pub mod cli;
-pub mod task_file;
+mod cli;
 
 fn main() {
     let cli = Cli::parse_args(None).unwrap_or_else(|e| {
         eprintln!("Cli error: {e}");
-        process::exit(1)
+        process::exit(e.exit_code())
     });
-    let mut task_file = TaskFile::load();
-
-    // Commands that don't need ids
-    match cli.command.as_str() {
-        "print" => {
-            task_file.print(cli.colored_output);
+    match &cli.command {
+        Command::Lists(action) => {
+            run_lists_cmd(action.as_ref(), &cli);
             process::exit(0)
         }
-        "add" if cli.task_ids.is_empty() => {
-            task_file.add_task(&cli.contents, &cli.add_to, &cli.task_ids);
-            task_file.save();
-            task_file.print(cli.colored_output);
+        Command::Init => {
+            let dir = env::current_dir().unwrap_or_else(|e| {
+                let e = Error::io("Unable to get the current directory", e);
+                eprintln!("{e}");
+                process::exit(e.exit_code())
+            });
+            match TaskFile::init(&dir) {
+                Ok(f) => println!("Created task file `{}`", f.display()),
+                Err(e) => {
+                    eprintln!("Init error: {e}");
+                    process::exit(e.exit_code())
+                }
+            }
             process::exit(0)
         }
-        "clear" => {
-            task_file.clear_dones();
-            task_file.save();
-            task_file.print(cli.colored_output);
+        // Work without loading, so they're usable when the task file is unreadable.
+        Command::Backups => {
+            println!("{}", TaskFile::format_backups(&get_file_path(&cli)));
             process::exit(0)
         }
+        Command::Restore(n) => {
+            if let Err(e) = TaskFile::restore_backup(&get_file_path(&cli), *n) {
+                eprintln!("Backup error: {e}");
+                process::exit(e.exit_code())
+            }
+        }
         _ => (),
     }
-
-    if let Err(e) = verify_ids(&cli.task_ids, &task_file, cli.allow_id_list) {
-        eprintln!("Id error: {e}");
-        process::exit(1)
+    let mut task_file = load(&cli);
+    for notice in task_file.get_notices() {
+        eprintln!("{notice}\n");
     }
 
-    let id = if cli.task_ids.contains(',') {
-        match Cli::parse_id_list(&cli.task_ids) {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("{e}");
-                process::exit(1)
-            }
+    let res = match &cli.command {
+        Command::Print | Command::Restore(_) => {
+            println!("{}", task_file.format_tasks(&cli.get_print_options()));
+            process::exit(0)
         }
-    } else if cli.task_ids.contains("..") {
-        match Cli::parse_id_range(&cli.task_ids) {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("{e}");
-                process::exit(1)
+        Command::Add { contents, parent } => {
+            let parent = parent.as_ref().map(|p| resolve(&task_file, p));
+            add_task(&mut task_file, &cli, contents, parent.as_ref())
+        }
+        Command::Do(ids) => task_file.mark_tasks(ids, true),
+        Command::Undo(ids) => task_file.mark_tasks(ids, false),
+        Command::Due { ids, due } => task_file.set_due(ids, *due),
+        Command::Prio { ids, priority } => task_file.set_priority(ids, *priority),
+        Command::Recur { ids, recurrence } => task_file.set_recurrence(ids, *recurrence),
+        Command::Tag { ids, tags } => task_file.tag_tasks(ids, tags, true),
+        Command::Untag { ids, tags } => task_file.tag_tasks(ids, tags, false),
+        Command::Sort(key) => {
+            task_file.sort_tasks(*key);
+            Ok(())
+        }
+        Command::Move { ids, to } => {
+            let to = resolve(&task_file, to);
+            task_file.move_tasks(ids, &to)
+        }
+        Command::Swap(id, other) => {
+            let (id, other) = (resolve(&task_file, id), resolve(&task_file, other));
+            task_file.swap_tasks(&id, &other)
+        }
+        Command::Append { ids, text } => task_file.append_to_tasks(ids, text),
+        Command::Edit { ids, text } => task_file.edit_tasks(ids, text),
+        Command::Delete(ids) => task_file.delete_tasks(ids),
+        Command::Note(id) => {
+            let id = resolve(&task_file, id);
+            let current = task_file.get_task_notes(&id).unwrap_or_default();
+            match read_notes(&current) {
+                Ok(notes) => task_file.set_notes(&id, &notes),
+                Err(e) => {
+                    eprintln!("Note error: {e}");
+                    process::exit(e.exit_code())
+                }
             }
         }
-    } else if cli.task_ids == "all" {
-        let i = format!("1..{}", task_file.get_task_count());
-        match Cli::parse_id_range(&i) {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("{e}");
-                process::exit(1)
+        Command::Show(id) => {
+            let id = resolve(&task_file, id);
+            match task_file.format_details(&id, cli.colored_output) {
+                Ok(details) => println!("{details}"),
+                Err(e) => {
+                    eprintln!("Id error: {e}");
+                    process::exit(e.exit_code())
+                }
             }
+            process::exit(0)
         }
-    } else {
-        cli.task_ids
-    };
-
-    if cli.command == "move" || cli.command == "swap" {
-        if let Err(e) = verify_ids(&cli.move_id, &task_file, cli.allow_id_list) {
-            eprintln!("Id error: {e}");
-            process::exit(1)
+        Command::Find(pattern) => {
+            println!(
+                "{}",
+                task_file.format_matches(pattern, &cli.get_print_options())
+            );
+            process::exit(0)
         }
-    }
-
-    // Commands that need ids
-    match cli.command.as_str() {
-        "add" => {
-            task_file.add_task(&cli.contents, &cli.add_to, &id);
+        Command::Start(id) => {
+            let id = resolve(&task_file, id);
+            if let Err(e) = task_file.start_timer(&id) {
+                eprintln!("Timer error: {e}");
+                process::exit(e.exit_code())
+            }
+            Ok(())
         }
-        "do" => {
-            task_file.mark_tasks(&id, true);
+        Command::Stop => {
+            match task_file.stop_timer() {
+                Ok(id) => println!("Stopped timing task {id}"),
+                Err(e) => {
+                    eprintln!("Timer error: {e}");
+                    process::exit(e.exit_code())
+                }
+            }
+            Ok(())
         }
-        "undo" => {
-            task_file.mark_tasks(&id, false);
+        Command::Time => {
+            println!("{}", task_file.format_time_report());
+            process::exit(0)
         }
-        "move" => {
-            task_file.move_task(&id, &cli.move_id);
+        Command::Clear => {
+            task_file.clear_dones();
+            Ok(())
         }
-        "swap" => {
-            task_file.swap_tasks(&id, &cli.move_id);
+        Command::Revert(steps) | Command::Redo(steps) => {
+            let res = if matches!(cli.command, Command::Revert(_)) {
+                task_file.revert(*steps)
+            } else {
+                task_file.redo(*steps)
+            };
+            if let Err(e) = res {
+                eprintln!("Journal error: {e}");
+                process::exit(e.exit_code())
+            }
+            Ok(())
         }
-        "append" => {
-            task_file.append_to_task(&id, &cli.contents);
+        Command::History => {
+            println!("{}", task_file.format_history(cli.colored_output));
+            process::exit(0)
         }
-        "edit" => {
-            task_file.edit_task(&id, &cli.contents);
+        Command::Archive(search) => {
+            println!("{}", task_file.format_archive(search, cli.colored_output));
+            process::exit(0)
         }
-        "delete" => {
-            task_file.delete_task(&id);
+        Command::Unarchive(n) => {
+            if let Err(e) = task_file.unarchive(*n) {
+                eprintln!("Archive error: {e}");
+                process::exit(e.exit_code())
+            }
+            Ok(())
         }
-        _ => {
-            println!("Error: Invalid command");
-            process::exit(1)
+        Command::Lists(_) | Command::Init | Command::Backups => {
+            unreachable!("handled before loading the task file")
         }
+    };
+
+    if let Err(e) = res {
+        eprintln!("{}: {e}", e.get_label());
+        process::exit(e.exit_code())
     }
+    save_and_print(&mut task_file, &cli);
+}
 
-    task_file.save();
-    task_file.print(cli.colored_output);
+/// Resolve a task given to a command taking a single id, exits when it doesn't exist.
+fn resolve(task_file: &TaskFile, target: &Target) -> TaskId {
+    task_file.resolve(target).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", e.get_label());
+        process::exit(e.exit_code())
+    })
 }
 
-fn verify_ids(ids: &str, tf: &TaskFile, allow_list: bool) -> Result<(), String> {
-    if ids.is_empty() {
-        return Err("No id provided".to_owned());
-    }
-    if ids == "all" {
-        return Ok(());
-    }
-    if ids == "0" {
-        return Err(format!("Invalid id `{ids}`"));
-    }
+fn get_file_path(cli: &Cli) -> PathBuf {
+    TaskFile::get_file_path(cli.list.as_deref(), cli.file.as_deref()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(e.exit_code())
+    })
+}
 
-    if (ids.contains(',') || ids.contains("..")) && !allow_list {
-        return Err("Command only accepts a single id".to_owned());
+/// Load the task file selected by `cli`, offering to restore the newest readable backup
+/// when it can't be read. Only asks when stdin is a terminal, otherwise points to
+/// `tsk restore`.
+fn load(cli: &Cli) -> TaskFile {
+    let e = match TaskFile::load(cli.list.as_deref(), cli.file.as_deref()) {
+        Ok(tf) => return tf,
+        Err(e) => e,
+    };
+    eprintln!("{e}");
+    if !matches!(e, Error::Corrupt { .. }) {
+        process::exit(e.exit_code())
     }
-    if ids.contains(|c: char| !c.is_digit(10) && c != '.' && c != ',') {
-        return Err("Id contains invalid characters".to_owned());
+
+    let file = get_file_path(cli);
+    let Some(n) = TaskFile::get_readable_backup(&file) else {
+        eprintln!("No readable backup of the task file was found");
+        process::exit(e.exit_code())
+    };
+
+    if !io::stdin().is_terminal() {
+        eprintln!("Backup {n} is readable, restore it with `tsk restore {n}`");
+        process::exit(e.exit_code())
     }
-    if ids.matches("..").count() > 1 {
-        return Err("Range pattern can only be used once".to_owned());
+
+    eprint!("Backup {n} is readable, restore it? [y/N] ");
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+        process::exit(e.exit_code())
     }
-    if ids.contains("..") && ids.contains(',') {
-        return Err("Range pattern cannot be used in a list".to_owned());
+
+    if let Err(e) = TaskFile::restore_backup(&file, n) {
+        eprintln!("{e}");
+        process::exit(e.exit_code())
     }
-    if ids.contains("...") || ids.contains(",,") {
-        return Err("Id list contains invalid patterns".to_owned());
+    eprintln!("Restored backup {n}\n");
+    TaskFile::load(cli.list.as_deref(), cli.file.as_deref()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(e.exit_code())
+    })
+}
+
+fn add_task(
+    task_file: &mut TaskFile,
+    cli: &Cli,
+    contents: &str,
+    parent: Option<&TaskId>,
+) -> Result<(), Error> {
+    let task = task_file.add_task(contents, cli.add_to, parent)?;
+    task.set_due(cli.due);
+    task.set_priority(cli.priority);
+    task.set_recurrence(cli.recurrence);
+    Ok(())
+}
+
+fn save_and_print(task_file: &mut TaskFile, cli: &Cli) {
+    if let Err(e) = task_file.save() {
+        eprintln!("{e}");
+        process::exit(e.exit_code())
     }
+    println!("{}", task_file.format_tasks(&cli.get_print_options()));
+}
 
-    let ids: Vec<&str> = if ids.contains("..") {
-        ids.split("..").collect()
-    } else {
-        ids.split(',').collect()
-    };
-    let t_count = tf.get_task_count();
-
-    for id in ids {
-        if !id.contains('.') {
-            match id.parse::<usize>() {
-                Ok(v) if v > t_count => return Err(format!("Id `{id}` is out of bounds")),
-                Ok(v) if v == 0 => return Err(format!("Invalid id `{id}`")),
-                Err(e) => return Err(format!("Invalid id `{id}\nError `{e}`")),
-                _ => (),
+fn run_lists_cmd(action: Option<&ListAction>, cli: &Cli) {
+    let dir = TaskFile::get_lists_dir().unwrap_or_else(|e| {
+        eprintln!("List error: {e}");
+        process::exit(e.exit_code())
+    });
+
+    let res = match action {
+        Some(ListAction::New(name)) => TaskFile::create_list(&dir, name),
+        Some(ListAction::Rename(old, new)) => TaskFile::rename_list(&dir, old, new),
+        Some(ListAction::Delete(name)) => TaskFile::delete_list(&dir, name),
+        None => {
+            let selected = cli.list.as_deref().unwrap_or(DEFAULT_LIST);
+            for l in TaskFile::get_lists(&dir) {
+                let mark = if l == selected { '*' } else { ' ' };
+                println!("{mark} {l}");
             }
-            continue;
+            Ok(())
         }
-        let i: Vec<&str> = id.split('.').collect();
-        match i[0].parse::<usize>() {
-            Ok(v) if v > t_count => return Err(format!("Id `{id}` is out of bounds")),
-            Ok(v) if v == 0 => return Err(format!("Invalid id `{id}`")),
-            Err(e) => return Err(format!("Invalid id `{id}\nError `{e}`")),
-            _ => (),
+    };
+
+    if let Err(e) = res {
+        eprintln!("List error: {e}");
+        process::exit(e.exit_code())
+    }
+}
+
+/// Create a temporary file holding `current`, returns its path. The name is random and
+/// the file must not exist yet, so a file planted in a shared temporary directory is never
+/// written to.
+fn create_note_file(current: &str) -> io::Result<PathBuf> {
+    loop {
+        let random = RandomState::new().hash_one(SystemTime::now());
+        let name = format!("tsk_note_{}_{random:016x}.md", process::id());
+        let path = env::temp_dir().join(name);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        match options.open(&path) {
+            Ok(mut f) => {
+                if let Err(e) = f.write_all(current.as_bytes()) {
+                    let _ = fs::remove_file(&path);
+                    return Err(e);
+                }
+                return Ok(path);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
         }
+    }
+}
+
+/// Read the notes of a task from stdin when it's piped, otherwise let the user edit
+/// `current` with their editor.
+fn read_notes(current: &str) -> Result<String, Error> {
+    if !io::stdin().is_terminal() {
+        return io::read_to_string(io::stdin()).map_err(|e| Error::io("Unable to read stdin", e));
+    }
 
-        let st_count = tf.get_subtask_count(i[0].parse::<usize>().unwrap() - 1);
-        match i[1].parse::<usize>() {
-            Ok(v) if v > st_count => return Err(format!("Id `{id}` is out of bounds")),
-            Ok(v) if v == 0 => return Err(format!("Invalid id `{id}`")),
-            Err(e) => return Err(format!("Invalid id `{id}\nError `{e}`")),
-            _ => (),
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_owned());
+    // Editors can be given with arguments, ex.: `code --wait`.
+    let mut args = editor.split_whitespace();
+    let program = args
+        .next()
+        .ok_or_else(|| Error::InvalidOption("$EDITOR is empty".to_owned()))?;
+
+    let path =
+        create_note_file(current).map_err(|e| Error::io("Unable to create a temporary file", e))?;
+
+    let status = process::Command::new(program)
+        .args(args)
+        .arg(&path)
+        .status();
+    let notes = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    match status {
+        Ok(s) if s.success() => {
+            notes.map_err(|e| Error::io("Unable to read the temporary file", e))
         }
+        Ok(s) => Err(Error::Command(format!(
+            "`{editor}` exited with {s}, notes not changed"
+        ))),
+        Err(e) => Err(Error::io(format!("Unable to run `{editor}`"), e)),
     }
-    Ok(())
 }
@@ -1,788 +1,3813 @@
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    date::{self, Recurrence},
+    error::Error,
+    id::{Selection, Step, Target, TaskId},
+    journal::{self, Journal},
+};
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveTime, Utc};
+use regex::Regex;
 use std::{
-    fs::{self, File, OpenOptions},
+    cmp::{Ordering, Reverse},
+    collections::BTreeMap,
+    env,
+    fs::{self, File, OpenOptions, TryLockError},
+    hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState},
+    io::{self, Write},
     path::{Path, PathBuf},
-    process,
+    str::FromStr,
+    sync::atomic::{self, AtomicU64},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+/// Name of the list used when none is selected.
+pub const DEFAULT_LIST: &str = "tasks";
+
+/// Project task file, only holds the project's default list.
+pub const PROJECT_FILE: &str = ".tsk.json";
+/// Ignored in projects, the lock, journal and backups of their task files.
+const GITIGNORE_ENTRIES: [&str; 2] = [".tsk.json.*", ".tsk/*.json.*"];
+
+/// Project directory, holds all the lists of a project.
+pub const PROJECT_DIR: &str = ".tsk";
+
+/// How long to wait for another tsk process to release the task file.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Version of the task file schema, bump it and add a migration when existing data has to
+/// change. New fields with a `#[serde(default)]` don't need one.
+const SCHEMA_VERSION: u64 = 3;
+
+/// How many characters of a task's unique id are printed.
+const SHORT_UID_LEN: usize = 8;
+
+/// How many previous versions of a task file are kept as backups.
+pub const MAX_BACKUPS: usize = 5;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaskFile {
+    version: u64,
+
+    #[serde(skip)]
     task_file_path: PathBuf,
+
     pub tasks: Vec<Task>,
+
+    /// Done tasks moved out of `tasks` by `clear`, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    archive: Vec<ArchivedTask>,
+
+    /// Held from `load` until the task file is dropped.
+    #[serde(skip)]
+    lock: Option<File>,
+
+    /// Hash of the file's contents when it was loaded, `None` if it wasn't loaded.
+    #[serde(skip)]
+    loaded_hash: Option<u64>,
+
+    /// Journal of the task file, `None` if it wasn't loaded.
+    #[serde(skip)]
+    journal: Option<Journal>,
+
+    /// The tasks as they were when loaded or last saved.
+    #[serde(skip)]
+    saved_tasks: Vec<Task>,
+
+    /// The archive as it was when loaded or last saved.
+    #[serde(skip)]
+    saved_archive: Vec<ArchivedTask>,
+
+    /// Operations done since the tasks were loaded or last saved.
+    #[serde(skip)]
+    operations: Vec<String>,
+
+    #[serde(skip)]
+    notices: Vec<String>,
+}
+
+impl Default for TaskFile {
+    fn default() -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            task_file_path: PathBuf::new(),
+            tasks: Vec::new(),
+            archive: Vec::new(),
+            lock: None,
+            loaded_hash: None,
+            journal: None,
+            saved_tasks: Vec::new(),
+            saved_archive: Vec::new(),
+            operations: Vec::new(),
+            notices: Vec::new(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Task {
+    /// Unique id that stays the same when the task changes position.
+    uid: String,
     contents: String,
     done: bool,
-    pub subtasks: Vec<SubTask>,
+    pub subtasks: Vec<Task>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<NaiveDate>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+
+    /// Longer text about the task, can span multiple lines.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    notes: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<DateTime<Utc>>,
+
+    /// When the task itself was last changed, not counting its state or subtasks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<DateTime<Utc>>,
+
+    /// When the task was marked done, `None` while it isn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed: Option<DateTime<Utc>>,
+
+    /// Recurring tasks come back undone when they're done.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recurrence: Option<Recurrence>,
+
+    /// When a recurring task was done, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    completions: Vec<DateTime<Utc>>,
+
+    /// Time spent working on the task, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    intervals: Vec<Interval>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct SubTask {
-    contents: String,
-    done: bool,
+/// Subtasks are tasks too, nested at any depth.
+pub type SubTask = Task;
+
+/// A done task moved to the archive, along with its subtasks.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchivedTask {
+    pub task: Task,
+
+    /// Unique id of the task's parent, empty for top level tasks.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    parent: String,
+}
+
+/// A period of work on a task, the timer is running while it has no end.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Interval {
+    start: DateTime<Utc>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+/// Where `add_task` puts a new task among its siblings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Position {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// What tasks can be sorted by, tasks without a value for it go last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Due,
+    /// Highest first.
+    Priority,
+}
+
+/// How `print` shows the tasks.
+/// A task is printed when it passes all the filters in use, or any of them with `any`.
+#[derive(Debug)]
+pub struct PrintOptions {
+    pub colored: bool,
+    pub show_uids: bool,
+
+    /// Print sibling tasks sorted by this key instead of by position.
+    pub sort_by: Option<SortKey>,
+
+    /// Only print the tasks due on or before this date, and their parents.
+    pub due_before: Option<NaiveDate>,
+
+    /// Only print the tasks with at least this priority, and their parents.
+    pub min_priority: Option<Priority>,
+
+    /// Only print the tasks with all of these tags, and their parents.
+    pub tags: Vec<String>,
+
+    /// Only print the tasks completed on or after this date, and their parents.
+    pub done_since: Option<NaiveDate>,
+
+    /// Only print the tasks created on or after this date, and their parents.
+    pub created_since: Option<NaiveDate>,
+
+    /// Only print the tasks modified on or after this date, and their parents.
+    pub modified_since: Option<NaiveDate>,
+
+    /// Only print the tasks that aren't done, and their parents.
+    pub pending: bool,
+
+    /// Only print the tasks that are done, and their parents.
+    pub done: bool,
+
+    /// Only print the tasks whose contents match, and their parents.
+    pub pattern: Option<Regex>,
+
+    /// Print the tasks that pass any of the filters instead of all of them.
+    pub any: bool,
+
+    /// Only print top level tasks, filters don't look at subtasks.
+    pub top_level_only: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            colored: true,
+            show_uids: false,
+            sort_by: None,
+            due_before: None,
+            min_priority: None,
+            tags: Vec::new(),
+            done_since: None,
+            created_since: None,
+            modified_since: None,
+            pending: false,
+            done: false,
+            pattern: None,
+            any: false,
+            top_level_only: false,
+        }
+    }
 }
 
 impl TaskFile {
-    pub fn load() -> Self {
-        let proj = ProjectDirs::from("tsk", "Emilly", "tsk").unwrap_or_else(|| {
-            eprintln!("Unable to retrieve/create the project directory");
-            process::exit(1)
-        });
+    /// Directory where the global task lists are stored.
+    fn get_data_dir() -> Result<PathBuf, Error> {
+        let proj = ProjectDirs::from("tsk", "Emilly", "tsk").ok_or_else(|| {
+            Error::io(
+                "Unable to retrieve/create the project directory",
+                io::ErrorKind::NotFound.into(),
+            )
+        })?;
+
+        let data_dir = proj.data_local_dir().to_path_buf();
+        fs::create_dir_all(&data_dir)
+            .map_err(|e| Error::io("Unable to create the data directory", e))?;
+        Ok(data_dir)
+    }
+
+    /// Walk up from `start` looking for a project task file, the same way git finds `.git`.
+    /// Returns the path of either a `.tsk.json` file or a `.tsk` directory. A `.tsk`
+    /// directory only counts when it holds the default list, `.tsk/tasks.json`.
+    pub fn find_project(start: &Path) -> Option<PathBuf> {
+        start.ancestors().find_map(|dir| {
+            let proj_dir = dir.join(PROJECT_DIR);
+            let proj_file = dir.join(PROJECT_FILE);
+
+            if Self::get_list_path(&proj_dir, DEFAULT_LIST).is_file() {
+                Some(proj_dir)
+            } else if proj_file.is_file() {
+                Some(proj_file)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn find_project_from_cwd() -> Option<PathBuf> {
+        let cwd = env::current_dir().ok()?;
+        Self::find_project(&cwd)
+    }
+
+    /// Directory where the task lists are stored, a project's `.tsk` directory if there's one.
+    pub fn get_lists_dir() -> Result<PathBuf, Error> {
+        match Self::find_project_from_cwd() {
+            Some(p) if p.is_dir() => Ok(p),
+            _ => Self::get_data_dir(),
+        }
+    }
+
+    /// Path of the task file to use for `list`, `None` for the default list.
+    /// A project's `.tsk.json` only holds its default list, other lists are the global ones.
+    pub fn get_path(list: Option<&str>) -> Result<PathBuf, Error> {
+        let name = list.unwrap_or(DEFAULT_LIST);
+        match Self::find_project_from_cwd() {
+            Some(p) if p.is_dir() => Ok(Self::get_list_path(&p, name)),
+            Some(p) if list.is_none() => Ok(p),
+            _ => Ok(Self::get_list_path(&Self::get_data_dir()?, name)),
+        }
+    }
+
+    /// Create a project task file inside `dir`, and ignore its companion files in the
+    /// `.gitignore` of `dir`.
+    pub fn init(dir: &Path) -> Result<PathBuf, Error> {
+        if dir.join(PROJECT_DIR).exists() || dir.join(PROJECT_FILE).exists() {
+            return Err(Error::Command(format!(
+                "A project task file already exists in `{}`",
+                dir.display()
+            )));
+        }
+        let file = dir.join(PROJECT_FILE);
+        Self::create_empty(&file)
+            .map_err(|e| e.with_context("Unable to create project task file"))?;
+        Self::extend_gitignore(dir).map_err(|e| Error::io("Unable to update `.gitignore`", e))?;
+        Ok(file)
+    }
+
+    /// Add the entries of `GITIGNORE_ENTRIES` missing from `<dir>/.gitignore`, creating it
+    /// if needed.
+    fn extend_gitignore(dir: &Path) -> io::Result<()> {
+        let path = dir.join(".gitignore");
+        let mut contents = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let missing: Vec<_> = GITIGNORE_ENTRIES
+            .into_iter()
+            .filter(|entry| !contents.lines().any(|l| l.trim() == *entry))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str("# tsk lock, journal and backups\n");
+        for entry in missing {
+            contents.push_str(entry);
+            contents.push('\n');
+        }
+        fs::write(path, contents)
+    }
+
+    /// Write a task file without tasks at `file`.
+    fn create_empty(file: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(&Self::default())
+            .map_err(|e| Error::io("Unable to serialize TaskFile struct", e.into()))?;
+        Self::write_atomic(file, &json).map_err(|e| Error::io("Unable to create the task file", e))
+    }
+
+    /// Path of the file that holds the list `name`.
+    pub fn get_list_path(dir: &Path, name: &str) -> PathBuf {
+        dir.join(format!("{name}.json"))
+    }
+
+    /// Path of the task file of `list`, or `file` if given.
+    pub fn get_file_path(list: Option<&str>, file: Option<&Path>) -> Result<PathBuf, Error> {
+        match file {
+            Some(f) => Ok(f.to_path_buf()),
+            None => Self::get_path(list),
+        }
+    }
 
-        let data_dir = proj.data_local_dir();
-        let mut file = data_dir.to_path_buf();
-        file.push("tasks.json");
+    /// Load the task file of `list`, or the one at `file` if given, `None` for both loads
+    /// the default list. The task file stays locked until the returned value is dropped.
+    /// A task file that isn't valid json returns `Error::Corrupt`, it can be brought back
+    /// with `get_readable_backup` and `restore_backup`.
+    pub fn load(list: Option<&str>, file: Option<&Path>) -> Result<Self, Error> {
+        let path = Self::get_file_path(list, file)?;
+
+        // Only the default list is created when missing.
+        match (file, list) {
+            (None, Some(list)) if list != DEFAULT_LIST && !path.exists() => {
+                return Err(Error::Command(format!(
+                    "List `{list}` doesn't exist\nCreate it with `tsk lists new {list}`"
+                )));
+            }
+            _ => (),
+        }
+        let file = path;
+
+        let lock = Self::lock(&file, LOCK_TIMEOUT)?;
+        let mut notices = Vec::new();
 
         match Path::try_exists(&file) {
             Ok(false) | Err(_) => {
-                println!("Creating new 'tasks' file\n");
-
-                let f = format!("{}/tasks.json", data_dir.to_str().unwrap());
-                File::create(f).is_err().then(|| {
-                    eprintln!("Unable to create 'tasks' file");
-                    process::exit(1);
-                });
+                notices.push("Creating new 'tasks' file".to_owned());
+                File::create(&file).map_err(|e| Error::io("Unable to create 'tasks' file", e))?;
+            }
+            Ok(true) => {
+                OpenOptions::new()
+                    .append(true)
+                    .read(true)
+                    .open(&file)
+                    .map_err(|e| {
+                        Error::io("Unable to open 'tasks' file for reading & writting", e)
+                    })?;
             }
-            Ok(true) => match OpenOptions::new().append(true).read(true).open(&file) {
-                Ok(_) => (),
-                Err(e) => {
-                    eprintln!("Unable to open 'tasks' file for reading & writting");
-                    eprintln!("Err: {e}");
-                    process::exit(1)
-                }
-            },
         }
 
+        let json =
+            fs::read_to_string(&file).map_err(|e| Error::io("Unable to read 'tasks' file", e))?;
+
         // If the file is empty return a default config.
-        if fs::metadata(&file)
-            .expect("File has been verified to be readable")
-            .len()
-            == 0
-        {
-            return Self {
-                task_file_path: file,
+        let mut tf = if json.is_empty() {
+            Self {
                 tasks: vec![Task {
+                    uid: new_uid(),
                     contents: "Create a new task file".to_owned(),
                     done: true,
-                    subtasks: Vec::new(),
+                    created: Some(Utc::now()),
+                    completed: Some(Utc::now()),
+                    ..Default::default()
                 }],
-            };
+                ..Default::default()
+            }
+        } else {
+            Self::from_json(&json)?
+        };
+
+        let journal = Journal::load(&Journal::get_path(&file)).unwrap_or_else(|e| {
+            notices.push(format!("{e}\nStarting a new journal"));
+            Journal::default()
+        });
+
+        tf.task_file_path = file;
+        tf.lock = Some(lock);
+        tf.loaded_hash = Some(Self::hash(&json));
+        tf.journal = Some(journal);
+        tf.saved_tasks = tf.tasks.clone();
+        tf.saved_archive = tf.archive.clone();
+        tf.notices = notices;
+        Ok(tf)
+    }
+
+    /// Messages about what `load` did that the user may want to know, like creating
+    /// the task file.
+    pub fn get_notices(&self) -> &[String] {
+        &self.notices
+    }
+
+    /// Deserialize a task file, migrating it from older schema versions.
+    fn from_json(json: &str) -> Result<Self, Error> {
+        let value = serde_json::from_str::<Value>(json)?;
+        Ok(serde_json::from_value(Self::migrate(value)?)?)
+    }
+
+    /// Upgrade the json of a task file to the current schema version.
+    fn migrate(mut value: Value) -> Result<Value, Error> {
+        let Some(obj) = value.as_object_mut() else {
+            return Err(Error::Corrupt {
+                msg: "Task file isn't a json object".to_owned(),
+                line: 1,
+                column: 1,
+            });
+        };
+
+        // Files without a version predate versioning.
+        let mut version = obj.get("version").and_then(Value::as_u64).unwrap_or(0);
+        if version > SCHEMA_VERSION {
+            return Err(Error::Command(format!(
+                "Task file uses schema version {version}, which is newer than this version of tsk supports ({SCHEMA_VERSION})"
+            )));
         }
 
-        let json = fs::read_to_string(file).expect("File has been verified to be readable");
-        match serde_json::from_str::<TaskFile>(&json) {
-            Ok(tf) => tf,
-            Err(err) => {
-                eprintln!("Unable to deserialize json string");
-                eprintln!("Err: {err}");
-                process::exit(1)
+        while version < SCHEMA_VERSION {
+            match version {
+                // The file's path was stored inside it, going stale when the file was moved.
+                0 => {
+                    obj.remove("task_file_path");
+                }
+                // Tasks got unique ids.
+                1 => {
+                    let tasks = obj.get_mut("tasks").and_then(Value::as_array_mut);
+                    for t in tasks.into_iter().flatten() {
+                        let subtasks = t.get_mut("subtasks").and_then(Value::as_array_mut);
+                        for st in subtasks.into_iter().flatten() {
+                            st["uid"] = new_uid().into();
+                        }
+                        t["uid"] = new_uid().into();
+                    }
+                }
+                // Subtasks can have their own subtasks.
+                2 => {
+                    let tasks = obj.get_mut("tasks").and_then(Value::as_array_mut);
+                    for t in tasks.into_iter().flatten() {
+                        let subtasks = t.get_mut("subtasks").and_then(Value::as_array_mut);
+                        for st in subtasks.into_iter().flatten() {
+                            st["subtasks"] = Value::Array(Vec::new());
+                        }
+                    }
+                }
+                _ => unreachable!("missing migration for schema version {version}"),
             }
+            version += 1;
         }
+        obj.insert("version".to_owned(), version.into());
+        Ok(value)
     }
 
-    pub fn save(&self) {
-        match serde_json::to_string_pretty(&self) {
-            Ok(json) => fs::write(&self.task_file_path, json)
-                .expect("File has been verified to be writable"),
-            Err(err) => {
-                eprintln!("Unable to serialize TaskFile struct");
-                eprintln!("Err: {err}");
-                process::exit(1)
+    /// Take an advisory lock on `file`, waiting up to `timeout` for other tsk processes.
+    /// A separate lock file is used since saving replaces the task file.
+    fn lock(file: &Path, timeout: Duration) -> Result<File, Error> {
+        let lock_path = Self::get_lock_path(file);
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| Error::io("Unable to open lock file", e))?;
+
+        let start = Instant::now();
+        loop {
+            match lock.try_lock() {
+                Ok(()) => return Ok(lock),
+                Err(TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                    thread::sleep(Duration::from_millis(50))
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(Error::Command(format!(
+                        "Task file `{}` is being used by another tsk process",
+                        file.display()
+                    )))
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(Error::io("Unable to lock the task file", e))
+                }
             }
-        };
+        }
     }
 
-    pub fn get_task_count(&self) -> usize {
-        self.tasks.iter().count()
+    /// Path of the file locked while `file` is in use, `<file>.lock`. The path is resolved
+    /// first so that every path to the same task file, ex.: through a symlink, shares a lock.
+    fn get_lock_path(file: &Path) -> PathBuf {
+        // A file that doesn't exist yet is resolved through its directory.
+        let file = fs::canonicalize(file)
+            .or_else(|e| match (file.parent(), file.file_name()) {
+                (Some(dir), Some(name)) if !dir.as_os_str().is_empty() => {
+                    fs::canonicalize(dir).map(|d| d.join(name))
+                }
+                (_, Some(name)) => env::current_dir().map(|d| d.join(name)),
+                _ => Err(e),
+            })
+            .unwrap_or_else(|_| file.to_path_buf());
+        let mut lock_path = file.into_os_string();
+        lock_path.push(".lock");
+        PathBuf::from(lock_path)
     }
 
-    pub fn get_subtask_count(&self, id: usize) -> usize {
-        self.tasks[id].subtasks.iter().count()
+    fn hash(json: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        json.hash(&mut hasher);
+        hasher.finish()
     }
 
-    pub fn get_task_contents(&self, id: &str) -> Option<String> {
-        if !id.contains('.') {
-            let id = Self::parse_id(id);
-            if let Some(t) = self.tasks.get(id) {
-                return Some(t.contents.clone());
-            }
-            return None;
+    /// Makes sure the task file wasn't changed by something else since it was loaded.
+    fn check_unmodified(&self) -> Result<(), Error> {
+        let Some(loaded_hash) = self.loaded_hash else {
+            return Ok(());
+        };
+        let json = match fs::read_to_string(&self.task_file_path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Error::io("Unable to read 'tasks' file", e)),
+        };
+
+        if Self::hash(&json) != loaded_hash {
+            return Err(Error::Command(format!(
+                "Task file `{}` was modified since it was loaded, changes not saved",
+                self.task_file_path.display()
+            )));
         }
-        let id = Self::parse_sub_id(id);
-        let t = self.tasks.get(id[0]);
-        if t.is_none() {
-            return None;
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.check_unmodified()?;
+
+        let json = serde_json::to_string_pretty(&self)
+            .map_err(|e| Error::io("Unable to serialize TaskFile struct", e.into()))?;
+
+        Self::rotate_backups(&self.task_file_path, &json)
+            .map_err(|e| Error::io("Unable to back up 'tasks' file", e))?;
+        Self::write_atomic(&self.task_file_path, &json)
+            .map_err(|e| Error::io("Unable to save 'tasks' file", e))?;
+
+        if self.loaded_hash.is_some() {
+            self.loaded_hash = Some(Self::hash(&json));
         }
-        let t = t.unwrap();
+        self.save_journal()
+    }
+
+    /// Record the operations done since the last save and save the journal.
+    fn save_journal(&mut self) -> Result<(), Error> {
+        let operations = std::mem::take(&mut self.operations);
+        let before = std::mem::replace(&mut self.saved_tasks, self.tasks.clone());
+        let archive_before = std::mem::replace(&mut self.saved_archive, self.archive.clone());
 
-        if let Some(st) = t.subtasks.get(id[1]) {
-            return Some(st.contents.clone());
+        let Some(journal) = self.journal.as_mut() else {
+            return Ok(());
+        };
+        if before != self.tasks || archive_before != self.archive {
+            // The archive is only recorded by the operations that change it.
+            let archive =
+                (archive_before != self.archive).then(|| (archive_before, self.archive.clone()));
+            journal.record(operations.join("; "), before, self.tasks.clone(), archive);
+        }
+        if !journal.is_changed() {
+            return Ok(());
         }
-        return None;
+
+        let json = journal.to_json()?;
+        let path = Journal::get_path(&self.task_file_path);
+        Self::write_atomic(&path, &json).map_err(|e| Error::io("Unable to save the journal", e))
     }
 
-    fn parse_id(id: &str) -> usize {
-        let id: usize = id.parse().expect("`id` verified to be usize in `main.rs`");
-        id - 1
+    /// Revert the last `steps` operations recorded in the journal.
+    pub fn revert(&mut self, steps: usize) -> Result<(), Error> {
+        let journal = self
+            .journal
+            .as_mut()
+            .ok_or_else(|| Error::Command("The task file has no journal".to_owned()))?;
+        if !journal.is_current(&self.tasks, &self.archive) {
+            return Err(Error::Command(
+                "The task file was changed outside of the journal, operations can't be reverted"
+                    .to_owned(),
+            ));
+        }
+        self.tasks = journal.revert(steps)?;
+        if let Some(archive) = journal.get_archive() {
+            self.archive = archive;
+        }
+        self.saved_tasks = self.tasks.clone();
+        self.saved_archive = self.archive.clone();
+        Ok(())
     }
 
-    fn parse_sub_id(id: &str) -> Vec<usize> {
-        let mut id: Vec<usize> = id
-            .split('.')
-            .map(|i| i.parse().expect("`i` verified to be usize in `main.rs`"))
-            .collect();
-        id[0] -= 1;
-        id[1] -= 1;
-        id
+    /// Redo the last `steps` reverted operations.
+    pub fn redo(&mut self, steps: usize) -> Result<(), Error> {
+        let journal = self
+            .journal
+            .as_mut()
+            .ok_or_else(|| Error::Command("The task file has no journal".to_owned()))?;
+        if !journal.is_current(&self.tasks, &self.archive) {
+            return Err(Error::Command(
+                "The task file was changed outside of the journal, operations can't be redone"
+                    .to_owned(),
+            ));
+        }
+        self.tasks = journal.redo(steps)?;
+        if let Some(archive) = journal.get_archive() {
+            self.archive = archive;
+        }
+        self.saved_tasks = self.tasks.clone();
+        self.saved_archive = self.archive.clone();
+        Ok(())
     }
 
-    pub fn print(&self, colored: bool) {
-        if self.tasks.len() == 0 {
-            println!("No tasks to print");
-            return;
+    /// Format the operations recorded in the journal, newest first.
+    pub fn format_history(&self, colored: bool) -> String {
+        let Some(journal) = &self.journal else {
+            return "No operations recorded".to_owned();
+        };
+        let entries: Vec<_> = journal.get_entries().collect();
+        if entries.is_empty() {
+            return "No operations recorded".to_owned();
         }
-        let _print = |id: &str, mark: &str, cont: &str| {
-            if mark.contains('X') && colored {
-                println!("{id}. \x1b[0;32m{mark} {cont} \x1b[0m");
+
+        let mut out: Vec<String> = Vec::new();
+        for (id, (e, applied)) in entries.iter().enumerate().rev() {
+            let line = format!(
+                "{}. {} {}: {}",
+                id + 1,
+                journal::format_ago(e.time),
+                e.operation,
+                journal.get_effects(id)
+            );
+            if *applied {
+                out.push(line);
             } else if colored {
-                println!("{id}. \x1b[0;31m{mark} {cont} \x1b[0m");
+                out.push(format!("\x1b[2m{line} (reverted)\x1b[0m"));
             } else {
-                println!("{id}. {mark} {cont}");
+                out.push(format!("{line} (reverted)"));
             }
+        }
+        out.join("\n")
+    }
+
+    /// Path of the `n`th newest backup of `file`, ex.: `tasks.json.1.bak`.
+    pub fn get_backup_path(file: &Path, n: usize) -> PathBuf {
+        let mut path = file.as_os_str().to_owned();
+        path.push(format!(".{n}.bak"));
+        PathBuf::from(path)
+    }
+
+    /// Keep the current contents of `file` as its newest backup, shifting the older ones
+    /// and dropping the oldest. Nothing is kept if `file` is empty or already holds `new_json`.
+    fn rotate_backups(file: &Path, new_json: &str) -> io::Result<()> {
+        let current = match fs::read_to_string(file) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
         };
+        if current.is_empty() || current == new_json {
+            return Ok(());
+        }
 
-        for (id, t) in self.tasks.iter().enumerate() {
-            let done = if t.done { "[X]" } else { "[ ]" };
-            let id = format!("{}", id + 1);
-            _print(&id, done, &t.contents);
+        for n in (1..MAX_BACKUPS).rev() {
+            let from = Self::get_backup_path(file, n);
+            if from.exists() {
+                fs::rename(&from, Self::get_backup_path(file, n + 1))?;
+            }
+        }
+        Self::write_atomic(&Self::get_backup_path(file, 1), &current)
+    }
 
-            if t.subtasks.len() == 0 {
+    /// Format the backups of `file`, newest first, with their age and task count.
+    pub fn format_backups(file: &Path) -> String {
+        let mut out: Vec<String> = Vec::new();
+        for n in 1..=MAX_BACKUPS {
+            let path = Self::get_backup_path(file, n);
+            let Ok(json) = fs::read_to_string(&path) else {
                 continue;
-            }
+            };
 
-            for (s_id, s_t) in t.subtasks.iter().enumerate() {
-                let done = if s_t.done { "[X]" } else { "[ ]" };
-                let s_id = format!("\t{}.{}", id, s_id + 1);
-                _print(&s_id, done, &s_t.contents);
-            }
+            let time = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            let tasks = match Self::from_json(&json) {
+                Ok(tf) => format!("{} task(s)", tf.tasks.len()),
+                Err(_) => "unreadable".to_owned(),
+            };
+            out.push(format!("{n}. {}, {tasks}", journal::format_ago(time)));
+        }
+
+        if out.is_empty() {
+            return format!("No backups of `{}`", file.display());
         }
+        out.join("\n")
     }
 
-    pub fn add_task(&mut self, contents: &str, to: &str, task_id: &str) {
-        let task = Task {
-            contents: contents.to_owned(),
-            done: false,
-            subtasks: Vec::new(),
+    /// Bring back the `n`th newest backup of `file`.
+    /// The replaced contents become the newest backup, so a restore can itself be undone.
+    pub fn restore_backup(file: &Path, n: usize) -> Result<(), Error> {
+        let _lock = Self::lock(file, LOCK_TIMEOUT)?;
+        let path = Self::get_backup_path(file, n);
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::Command(format!("Backup {n} doesn't exist")))
+            }
+            Err(e) => return Err(Error::io(format!("Unable to read backup {n}"), e)),
         };
+        Self::from_json(&json).map_err(|e| e.with_context(&format!("Backup {n} is unreadable")))?;
 
-        if task_id.is_empty() {
-            if to == "top" {
-                self.tasks.insert(0, task);
-            } else {
-                self.tasks.push(task);
-            }
-            return;
-        }
-        let task = SubTask::from_task(task);
-        let task_id = Self::parse_id(task_id);
+        Self::rotate_backups(file, &json)
+            .and_then(|_| Self::write_atomic(file, &json))
+            .map_err(|e| Error::io(format!("Unable to restore backup {n}"), e))
+    }
 
-        for (id, t) in self.tasks.iter_mut().enumerate() {
-            if id != task_id {
-                continue;
+    /// Returns the newest backup of `file` that can be loaded.
+    pub fn get_readable_backup(file: &Path) -> Option<usize> {
+        (1..=MAX_BACKUPS).find(|n| {
+            fs::read_to_string(Self::get_backup_path(file, *n))
+                .is_ok_and(|json| Self::from_json(&json).is_ok())
+        })
+    }
+
+    /// Write `contents` to a temporary file next to `path`, then rename it over `path`.
+    /// A crash midway leaves either the old file or the new one, never a truncated file.
+    /// When `path` is a symlink its target is replaced, keeping the link and the
+    /// permissions of the file.
+    fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+        // A file that doesn't exist yet can't be resolved and is created as is.
+        let path = &fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let permissions = fs::metadata(path).map(|m| m.permissions()).ok();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let write = || -> io::Result<()> {
+            let mut f = File::create(&tmp)?;
+            if let Some(permissions) = &permissions {
+                f.set_permissions(permissions.clone())?;
             }
-            if to == "top" {
-                t.subtasks.insert(0, task);
-            } else {
-                t.subtasks.push(task);
+            f.write_all(contents.as_bytes())?;
+            f.sync_all()?;
+            fs::rename(&tmp, path)?;
+
+            // Make the rename itself durable.
+            #[cfg(unix)]
+            if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                File::open(dir)?.sync_all()?;
             }
-            t.done = false;
-            return;
+            Ok(())
+        };
+
+        let res = write();
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp);
         }
+        res
     }
 
-    pub fn mark_tasks(&mut self, ids: &str, done: bool) {
-        let (ids, sub_ids) = if ids.contains('.') {
-            let id1: Vec<usize> = ids
-                .split(',')
-                .filter(|s| !s.contains('.'))
-                .map(|s| s.parse().expect("ids verified to be usize in main.rs"))
-                .collect();
+    /// List names may only contain alphanumeric characters, `-` and `_`.
+    pub fn is_valid_list_name(name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    }
 
-            let id2: Vec<String> = ids
-                .split(',')
-                .filter(|s| s.contains('.'))
-                .map(|s| s.to_string())
-                .collect();
-            (id1, id2)
-        } else {
-            let ids = ids
-                .split(',')
-                .map(|s| s.parse().expect("ids verified to be usize in main.rs"))
-                .collect();
-            (ids, vec![])
+    /// Returns the sorted names of the lists inside `dir`.
+    pub fn get_lists(dir: &Path) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
         };
+        let mut lists: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .filter_map(|p| Some(p.file_stem()?.to_str()?.to_owned()))
+            .filter(|n| Self::is_valid_list_name(n))
+            .collect();
 
-        for i in sub_ids {
-            let i = Self::parse_sub_id(&i);
-            let task = &mut self.tasks[i[0]];
-            task.subtasks[i[1]].done = done;
+        lists.sort();
+        lists
+    }
 
-            // If all the subtasks are done, so will the task.
-            let done_count = task.subtasks.iter().filter(|t| t.done).count();
-            task.done = done_count == task.subtasks.len()
+    pub fn create_list(dir: &Path, name: &str) -> Result<(), Error> {
+        if !Self::is_valid_list_name(name) {
+            return Err(Error::InvalidOption(format!("Invalid list name `{name}`")));
         }
-
-        for i in ids {
-            let i = i - 1;
-            let task = &mut self.tasks[i];
-            task.done = done;
-
-            // If a task is marked done, so will it's subtasks.
-            if task.subtasks.is_empty() || !done {
-                continue;
-            }
-            for t in task.subtasks.iter_mut() {
-                t.done = true
-            }
+        let file = Self::get_list_path(dir, name);
+        let _lock = Self::lock(&file, LOCK_TIMEOUT)?;
+        if file.exists() {
+            return Err(Error::Command(format!("List `{name}` already exists")));
         }
+        Self::create_empty(&file)
+            .map_err(|e| e.with_context(&format!("Unable to create list `{name}`")))
     }
 
-    pub fn move_task(&mut self, from: &str, to: &str) {
-        if !from.contains('.') {
-            let from = Self::parse_id(from);
-            let task = self.tasks.remove(from);
-
-            if !to.contains('.') {
-                let to: usize = Self::parse_id(to);
+    /// Rename the list `old` along with its journal and backups. Its lock file stays, as
+    /// another process may be waiting on it.
+    pub fn rename_list(dir: &Path, old: &str, new: &str) -> Result<(), Error> {
+        if old == DEFAULT_LIST {
+            return Err(Error::Command(
+                "The default list can't be renamed".to_owned(),
+            ));
+        } else if !Self::is_valid_list_name(new) {
+            return Err(Error::InvalidOption(format!("Invalid list name `{new}`")));
+        }
+        let from = Self::get_list_path(dir, old);
+        let to = Self::get_list_path(dir, new);
+
+        let _from_lock = Self::lock(&from, LOCK_TIMEOUT)?;
+        let _to_lock = Self::lock(&to, LOCK_TIMEOUT)?;
+        if !from.exists() {
+            return Err(Error::Command(format!("List `{old}` doesn't exist")));
+        } else if to.exists() {
+            return Err(Error::Command(format!("List `{new}` already exists")));
+        }
 
-                // Adjust for when `from` gets removed from the array.
-                self.tasks.insert(to, task);
-                return;
+        let rename = |f: &Path, t: &Path| {
+            fs::rename(f, t).map_err(|e| Error::io(format!("Unable to rename list `{old}`"), e))
+        };
+        let (from_name, to_name) = (format!("{old}.json"), format!("{new}.json"));
+        let lock_path = Self::get_lock_path(&from);
+        for f in Self::get_companion_files(&from) {
+            let suffix = f
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix(&from_name));
+            match suffix {
+                Some(suffix) if f.file_name() != lock_path.file_name() => {
+                    rename(&f, &dir.join(format!("{to_name}{suffix}")))?
+                }
+                _ => (),
             }
-            let sub_task = SubTask::from_task(task);
-            let to = Self::parse_sub_id(to);
+        }
+        rename(&from, &to)
+    }
 
-            let task_id = if from > to[0] { to[0] } else { to[0] - 1 };
-            self.tasks[task_id].subtasks.insert(to[1], sub_task);
-            return;
+    /// Delete the list `name` along with its journal and backups. Its lock file stays, as
+    /// another process may be waiting on it.
+    pub fn delete_list(dir: &Path, name: &str) -> Result<(), Error> {
+        if name == DEFAULT_LIST {
+            return Err(Error::Command(
+                "The default list can't be deleted".to_owned(),
+            ));
+        }
+        let file = Self::get_list_path(dir, name);
+        let _lock = Self::lock(&file, LOCK_TIMEOUT)?;
+        if !file.exists() {
+            return Err(Error::Command(format!("List `{name}` doesn't exist")));
         }
-        let from = Self::parse_sub_id(from);
-        let sub_task = self.tasks[from[0]].subtasks.remove(from[1]);
 
-        if !to.contains('.') {
-            let to = Self::parse_id(to);
-            let task = Task::from_sub_task(sub_task);
-            self.tasks.insert(to, task);
-            return;
+        let lock_path = Self::get_lock_path(&file);
+        for f in Self::get_companion_files(&file).iter().chain([&file]) {
+            if f.file_name() == lock_path.file_name() {
+                continue;
+            }
+            fs::remove_file(f)
+                .map_err(|e| Error::io(format!("Unable to delete list `{name}`"), e))?;
         }
-        let to = Self::parse_sub_id(to);
-        self.tasks[to[0]].subtasks.insert(to[1], sub_task);
+        Ok(())
     }
 
-    pub fn swap_tasks(&mut self, task1: &str, task2: &str) {
-        let t1 = if task1.contains('.') {
-            let id = Self::parse_sub_id(task1);
+    /// Files kept next to `file` that belong to it, like `tasks.json.lock`.
+    fn get_companion_files(file: &Path) -> Vec<PathBuf> {
+        let (Some(dir), Some(name)) = (file.parent(), file.file_name()) else {
+            return Vec::new();
+        };
+        let mut prefix = name.to_owned();
+        prefix.push(".");
+        let prefix = prefix.to_string_lossy().into_owned();
 
-            let t = self.tasks[id[0]].subtasks[id[1]].clone();
-            Task::from_sub_task(t)
-        } else {
-            let id = Self::parse_id(task1);
-            self.tasks[id].clone()
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
         };
+        entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with(&prefix))
+            .map(|e| e.path())
+            .collect()
+    }
 
-        let t2 = if task2.contains('.') {
-            let id = Self::parse_sub_id(task2);
-            let t = self.tasks[id[0]].subtasks[id[1]].clone();
+    pub fn get_task_count(&self) -> usize {
+        self.tasks.len()
+    }
 
-            self.tasks[id[0]].subtasks[id[1]] = SubTask::from_task(t1);
-            Task::from_sub_task(t)
-        } else {
-            let id = Self::parse_id(task2);
-            let t = self.tasks[id].clone();
+    /// Returns how many subtasks the task at `id` has, an empty `id` counts the top level tasks.
+    /// `id` is zero based, as returned by `TaskId::get_path`.
+    pub fn get_subtask_count(&self, id: &[usize]) -> usize {
+        if id.is_empty() {
+            return self.tasks.len();
+        }
+        self.get(id).map_or(0, |t| t.subtasks.len())
+    }
 
-            self.tasks[id] = t1;
-            t
+    /// Returns the ids of the tasks `target` refers to. Positional ids are returned as is,
+    /// `verify_id` checks that they exist.
+    fn resolve_all(&self, target: &Target) -> Result<Vec<TaskId>, Error> {
+        let with_state = |done| {
+            let found: Vec<TaskId> = self
+                .get_all()
+                .into_iter()
+                .filter(|(_, t)| t.done == done)
+                .map(|(id, _)| id)
+                .collect();
+            if found.is_empty() {
+                return Err(Error::OutOfBounds(format!("No task is {target}")));
+            }
+            Ok(found)
         };
 
-        if task1.contains('.') {
-            let id = Self::parse_sub_id(task1);
-            self.tasks[id[0]].subtasks[id[1]] = SubTask::from_task(t2);
-        } else {
-            let id = Self::parse_id(task1);
-            self.tasks[id] = t2;
+        match target {
+            Target::Id(id) => Ok(vec![id.clone()]),
+            Target::Path(steps) => self.resolve_path(target, steps),
+            Target::Done => with_state(true),
+            Target::Pending => with_state(false),
+            Target::Uid(prefix) => {
+                let mut found = self
+                    .get_all()
+                    .into_iter()
+                    .filter(|(_, t)| t.uid.starts_with(prefix.as_str()));
+                match (found.next(), found.next()) {
+                    (Some((id, _)), None) => Ok(vec![id]),
+                    (None, _) => Err(Error::OutOfBounds(format!(
+                        "No task has the unique id `{target}`"
+                    ))),
+                    (Some(_), Some(_)) => Err(Error::InvalidId(format!(
+                        "Unique id `{target}` matches more than one task"
+                    ))),
+                }
+            }
+            Target::Tag(tag) => {
+                let found: Vec<TaskId> = self
+                    .get_all()
+                    .into_iter()
+                    .filter(|(_, t)| t.tags.contains(tag))
+                    .map(|(id, _)| id)
+                    .collect();
+                if found.is_empty() {
+                    return Err(Error::OutOfBounds(format!(
+                        "No task has the tag `{target}`"
+                    )));
+                }
+                Ok(found)
+            }
         }
     }
 
-    pub fn append_to_task(&mut self, id: &str, content: &str) {
-        let content = format!(" {content}");
-
-        if !id.contains('.') {
-            let id = Self::parse_id(id);
-
-            self.tasks[id].contents.push_str(&content);
-            self.tasks[id].done = false;
-            return;
+    /// Follow `steps` from the top level down, `target` is what they were parsed from.
+    fn resolve_path(&self, target: &Target, steps: &[Step]) -> Result<Vec<TaskId>, Error> {
+        let out_of_bounds = || Error::OutOfBounds(format!("Id `{target}` is out of bounds"));
+        // `None` stands for the top level.
+        let mut parents: Vec<Option<TaskId>> = vec![None];
+        for step in steps {
+            let mut found = Vec::new();
+            for parent in parents {
+                let count = match &parent {
+                    Some(p) => {
+                        self.get(p.get_path()).ok_or_else(out_of_bounds)?;
+                        self.get_subtask_count(p.get_path())
+                    }
+                    None => self.tasks.len(),
+                };
+                let child = |i| {
+                    parent
+                        .as_ref()
+                        .map_or_else(|| TaskId::top(i), |p| p.child(i))
+                };
+                match *step {
+                    Step::Nth(i) => found.push(child(i)),
+                    Step::FromEnd(i) if i > count => return Err(out_of_bounds()),
+                    Step::FromEnd(i) => found.push(child(count - i)),
+                    Step::Every => found.extend((0..count).map(child)),
+                }
+            }
+            parents = found.into_iter().map(Some).collect();
         }
-        let id = Self::parse_sub_id(id);
-        let task = &mut self.tasks[id[0]];
 
-        task.subtasks[id[1]].contents.push_str(&content);
-        task.subtasks[id[1]].done = false;
-        task.done = false
+        let found: Vec<TaskId> = parents.into_iter().flatten().collect();
+        if found.is_empty() {
+            return Err(Error::OutOfBounds(format!("`{target}` selects no task")));
+        }
+        Ok(found)
     }
 
-    pub fn edit_task(&mut self, id: &str, new_content: &str) {
-        let new_content = new_content.to_owned();
-
-        if !id.contains('.') {
-            let id = Self::parse_id(id);
-            self.tasks[id].contents = new_content;
-            self.tasks[id].done = false;
-            return;
+    /// Returns the id of the single task `target` refers to.
+    pub fn resolve(&self, target: &Target) -> Result<TaskId, Error> {
+        match self.resolve_all(target)?.as_slice() {
+            [id] => Ok(id.clone()),
+            _ => Err(Error::InvalidId(format!(
+                "`{target}` selects more than one task"
+            ))),
         }
-        let id = Self::parse_sub_id(id);
-        let task = &mut self.tasks[id[0]];
-
-        task.subtasks[id[1]].contents = new_content;
-        task.subtasks[id[1]].done = false;
-        task.done = false;
     }
 
-    pub fn delete_task(&mut self, id: &str) {
-        if !id.contains('.') {
-            let id = Self::parse_id(id);
-            self.tasks.remove(id);
-            return;
+    /// Returns the ids of the tasks in `selection`, sorted with parents before their
+    /// subtasks. Fails if any of them doesn't exist.
+    pub fn select(&self, selection: &Selection) -> Result<Vec<TaskId>, Error> {
+        let ids = selection.expand(|t| self.resolve_all(t), self.tasks.len())?;
+        for id in ids.iter() {
+            self.verify_id(id)?;
         }
-        let id = Self::parse_sub_id(id);
-        self.tasks[id[0]].subtasks.remove(id[1]);
+        Ok(ids)
     }
 
-    pub fn clear_dones(&mut self) {
-        self.tasks = self
-            .tasks
-            .iter()
-            .filter(|t| !t.done)
-            .map(|t| t.to_owned())
-            .collect();
+    /// Makes sure the task at `id` exists.
+    pub fn verify_id(&self, id: &TaskId) -> Result<(), Error> {
+        match self.get(id.get_path()) {
+            Some(_) => Ok(()),
+            None => Err(Error::OutOfBounds(format!("Id `{id}` is out of bounds"))),
+        }
+    }
 
-        for t in self.tasks.iter_mut() {
-            if t.subtasks.is_empty() {
-                continue;
+    /// Like `verify_id`, but `id` may also be one past the last of its siblings,
+    /// for moving a task to the end of a list.
+    fn verify_destination(&self, id: &TaskId) -> Result<(), Error> {
+        let siblings = match id.get_parent() {
+            Some(parent) => {
+                self.verify_id(&parent)?;
+                self.get_subtask_count(parent.get_path())
             }
-            t.subtasks = t
-                .subtasks
-                .iter()
-                .filter(|st| !st.done)
-                .map(|st| st.to_owned())
-                .collect()
+            None => self.tasks.len(),
+        };
+        if id.get_index() > siblings {
+            return Err(Error::OutOfBounds(format!("Id `{id}` is out of bounds")));
         }
+        Ok(())
     }
-}
 
-impl Task {
-    pub fn from_sub_task(sub: SubTask) -> Self {
-        Self {
-            contents: sub.contents,
-            done: sub.done,
-            subtasks: vec![],
+    /// Returns every task along with its positional id, at any depth, parents first.
+    fn get_all(&self) -> Vec<(TaskId, &Task)> {
+        fn collect<'a>(
+            tasks: &'a [Task],
+            parent: Option<&TaskId>,
+            all: &mut Vec<(TaskId, &'a Task)>,
+        ) {
+            for (i, t) in tasks.iter().enumerate() {
+                let id = parent.map_or_else(|| TaskId::top(i), |p| p.child(i));
+                all.push((id.clone(), t));
+                collect(&t.subtasks, Some(&id), all);
+            }
         }
+
+        let mut all = Vec::new();
+        collect(&self.tasks, None, &mut all);
+        all
     }
-}
 
-impl SubTask {
-    pub fn from_task(task: Task) -> Self {
-        return Self {
-            contents: task.contents,
-            done: task.done,
-        };
+    /// Tag names start with a letter and may contain alphanumeric characters, `-` and `_`.
+    pub fn is_valid_tag(tag: &str) -> bool {
+        tag.starts_with(char::is_alphabetic)
+            && tag
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Split the `+tag` words out of `contents`, returns the remaining contents and the tags.
+    fn take_tags(contents: &str) -> (String, Vec<String>) {
+        let mut tags: Vec<String> = Vec::new();
+        let words: Vec<&str> = contents
+            .split(' ')
+            .filter(|w| match w.strip_prefix('+') {
+                Some(tag) if Self::is_valid_tag(tag) => {
+                    if !tags.iter().any(|t| t == tag) {
+                        tags.push(tag.to_owned());
+                    }
+                    false
+                }
+                _ => true,
+            })
+            .collect();
+        (words.join(" ").trim().to_owned(), tags)
+    }
 
-    fn get_test_task_file() -> TaskFile {
-        TaskFile {
-            task_file_path: PathBuf::new(),
-            tasks: vec![
-                Task {
-                    contents: "one".to_owned(),
-                    done: false,
-                    subtasks: vec![
-                        SubTask {
-                            contents: "one".to_owned(),
-                            done: false,
-                        },
-                        SubTask {
-                            contents: "two".to_owned(),
-                            done: false,
-                        },
-                    ],
-                },
-                Task {
-                    contents: "two".to_owned(),
-                    done: false,
-                    subtasks: vec![
-                        SubTask {
-                            contents: "one".to_owned(),
-                            done: false,
-                        },
-                        SubTask {
-                            contents: "two".to_owned(),
-                            done: false,
-                        },
-                    ],
-                },
-            ],
+    /// Returns the task at `id`, `None` if it doesn't exist.
+    pub fn get_task(&self, id: &TaskId) -> Option<&Task> {
+        self.get(id.get_path())
+    }
+
+    pub fn get_task_contents(&self, id: &TaskId) -> Option<String> {
+        self.get_task(id).map(|t| t.contents.clone())
+    }
+
+    pub fn get_task_notes(&self, id: &TaskId) -> Option<String> {
+        self.get_task(id).map(|t| t.notes.clone())
+    }
+
+    fn get(&self, id: &[usize]) -> Option<&Task> {
+        let (first, rest) = id.split_first()?;
+        let mut task = self.tasks.get(*first)?;
+        for i in rest {
+            task = task.subtasks.get(*i)?;
         }
+        Some(task)
     }
 
-    fn get_done_test_task_file() -> TaskFile {
-        TaskFile {
-            task_file_path: PathBuf::new(),
-            tasks: vec![
-                Task {
-                    contents: "one".to_owned(),
-                    done: true,
-                    subtasks: vec![
-                        SubTask {
-                            contents: "one".to_owned(),
-                            done: true,
-                        },
-                        SubTask {
-                            contents: "two".to_owned(),
-                            done: true,
-                        },
-                    ],
-                },
-                Task {
-                    contents: "two".to_owned(),
-                    done: true,
-                    subtasks: vec![
-                        SubTask {
-                            contents: "one".to_owned(),
-                            done: true,
-                        },
-                        SubTask {
-                            contents: "two".to_owned(),
-                            done: true,
-                        },
-                    ],
-                },
-            ],
+    fn get_mut(&mut self, id: &[usize]) -> &mut Task {
+        let mut task = &mut self.tasks[id[0]];
+        for i in &id[1..] {
+            task = &mut task.subtasks[*i];
+        }
+        task
+    }
+
+    /// Returns the subtasks of the task at `parent`, or the top level tasks if it's empty.
+    fn get_siblings_mut(&mut self, parent: &[usize]) -> &mut Vec<Task> {
+        if parent.is_empty() {
+            return &mut self.tasks;
+        }
+        &mut self.get_mut(parent).subtasks
+    }
+
+    /// Unmark the parents of the task at `id`, since they now have a task that isn't done.
+    fn undo_parents(&mut self, id: &[usize]) {
+        for depth in 1..id.len() {
+            self.get_mut(&id[..depth]).set_done(false);
+        }
+    }
+
+    /// Format the tasks the way `print` shows them, one per line.
+    pub fn format_tasks(&self, opts: &PrintOptions) -> String {
+        if self.tasks.is_empty() {
+            return "No tasks to print".to_owned();
+        }
+        let today = date::today();
+
+        let mut stack = Self::get_shown("", &self.tasks, opts);
+        if stack.is_empty() {
+            return "No tasks match".to_owned();
+        }
+        stack.reverse();
+
+        let mut out: Vec<String> = Vec::new();
+        while let Some((id, t)) = stack.pop() {
+            let depth = id.matches('.').count();
+            let indented = format!("{}{id}", "\t".repeat(depth));
+            out.push(Self::format_task_line(&indented, t, opts, today));
+
+            if !opts.top_level_only {
+                let subtasks = Self::get_shown(&format!("{id}."), &t.subtasks, opts);
+                stack.extend(subtasks.into_iter().rev());
+            }
+        }
+        out.join("\n")
+    }
+
+    /// Format the line of the task `t`, `id` is used as is.
+    fn format_task_line(id: &str, t: &Task, opts: &PrintOptions, today: NaiveDate) -> String {
+        let mark = if t.done { "[X]" } else { "[ ]" };
+        let mut cont = match t.priority {
+            Some(p) => format!("{} {}", p.get_mark(), t.contents),
+            None => t.contents.clone(),
+        };
+        for tag in t.tags.iter() {
+            cont.push_str(&format!(" +{tag}"));
+        }
+        if !t.notes.is_empty() {
+            cont.push_str(" [notes]");
+        }
+        if let Some(r) = t.recurrence {
+            cont.push_str(&format!(" ({r})"));
+        }
+        if let Some(i) = t.intervals.last().filter(|i| i.end.is_none()) {
+            let secs = (Utc::now() - i.start).num_seconds();
+            cont.push_str(&format!(" [running {}]", date::format_duration(secs)));
+        }
+
+        let due = t.due.map(|d| (d, d.cmp(&today)));
+        match due {
+            Some((_, Ordering::Equal)) => cont.push_str(" (due today)"),
+            Some((d, Ordering::Less)) if !t.done => cont.push_str(&format!(" (overdue {d})")),
+            Some((d, _)) => cont.push_str(&format!(" (due {d})")),
+            None => (),
+        }
+        if opts.show_uids {
+            cont.push_str(&format!(
+                " @{}",
+                t.uid.get(..SHORT_UID_LEN).unwrap_or(&t.uid)
+            ));
+        }
+
+        let color = match due {
+            _ if t.done => "32",
+            Some((_, Ordering::Less)) => "1;35",
+            Some((_, Ordering::Equal)) => "1;33",
+            _ => "31",
+        };
+        if opts.colored {
+            format!("{id}. \x1b[0;{color}m{mark} {cont} \x1b[0m")
+        } else {
+            format!("{id}. {mark} {cont}")
+        }
+    }
+
+    /// Format the tasks whose contents match `pattern`, with their ids and without
+    /// their parents.
+    pub fn format_matches(&self, pattern: &Regex, opts: &PrintOptions) -> String {
+        let matches = self.get_matches(pattern);
+        if matches.is_empty() {
+            return "No tasks match".to_owned();
+        }
+        let today = date::today();
+        let lines: Vec<String> = matches
+            .into_iter()
+            .map(|(id, t)| Self::format_task_line(&id.to_string(), t, opts, today))
+            .collect();
+        lines.join("\n")
+    }
+
+    /// Returns the tasks and subtasks whose contents match `pattern`, in print order.
+    fn get_matches(&self, pattern: &Regex) -> Vec<(TaskId, &Task)> {
+        self.get_all()
+            .into_iter()
+            .filter(|(_, t)| pattern.is_match(&t.contents))
+            .collect()
+    }
+
+    /// Format everything about the task at `id`, including its notes.
+    pub fn format_details(&self, id: &TaskId, colored: bool) -> Result<String, Error> {
+        self.verify_id(id)?;
+        let t = self.get(id.get_path()).expect("id verified by `verify_id`");
+        let (mark, color) = if t.done { ("[X]", "32") } else { ("[ ]", "31") };
+
+        let mut out: Vec<String> = Vec::new();
+        if colored {
+            out.push(format!("{id}. \x1b[0;{color}m{mark} {}\x1b[0m", t.contents));
+        } else {
+            out.push(format!("{id}. {mark} {}", t.contents));
+        }
+        out.push(format!("Unique id: {}", t.uid));
+
+        if let Some(due) = t.due {
+            out.push(format!("Due: {due}"));
+        }
+        if let Some(p) = t.priority {
+            out.push(format!("Priority: {}", p.get_name()));
+        }
+        if !t.tags.is_empty() {
+            let tags: Vec<String> = t.tags.iter().map(|t| format!("+{t}")).collect();
+            out.push(format!("Tags: {}", tags.join(" ")));
+        }
+        if let Some(r) = t.recurrence {
+            out.push(format!("Recurs: {r}"));
+        }
+        if let Some(last) = t.completions.last() {
+            let last = last.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+            out.push(format!(
+                "Done {} time(s), last on {last}",
+                t.completions.len()
+            ));
+        }
+        if !t.intervals.is_empty() {
+            let running = if t.is_running() { " (running)" } else { "" };
+            let time = date::format_duration(t.get_tracked_secs());
+            out.push(format!("Time tracked: {time}{running}"));
+        }
+        if !t.subtasks.is_empty() {
+            let done = t.subtasks.iter().filter(|t| t.done).count();
+            out.push(format!("Subtasks: {} ({done} done)", t.subtasks.len()));
+        }
+
+        let times = [
+            ("Created", t.created),
+            ("Modified", t.modified),
+            ("Completed", t.completed),
+        ];
+        for (name, time) in times {
+            if let Some(time) = time {
+                let time = time.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+                out.push(format!("{name}: {time}"));
+            }
+        }
+
+        if !t.notes.is_empty() {
+            out.push(format!("\n{}", t.notes));
+        }
+        Ok(out.join("\n"))
+    }
+
+    /// The tasks among `tasks` that `print` shows, in the order they're printed,
+    /// along with their ids. `parent` is the id of their parent followed by a `.`.
+    fn get_shown<'a>(
+        parent: &str,
+        tasks: &'a [Task],
+        opts: &PrintOptions,
+    ) -> Vec<(String, &'a Task)> {
+        let mut shown: Vec<(String, &Task)> = tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.is_shown(opts))
+            .map(|(id, t)| (format!("{parent}{}", id + 1), t))
+            .collect();
+
+        if let Some(key) = opts.sort_by {
+            shown.sort_by(|(_, a), (_, b)| a.compare(b, key));
+        }
+        shown
+    }
+
+    /// Reorder the tasks at every depth by `key`.
+    /// Tasks that compare equal keep their order.
+    pub fn sort_tasks(&mut self, key: SortKey) {
+        self.operations
+            .push(format!("sort --by={}", key.get_name()));
+
+        fn sort(tasks: &mut [Task], key: SortKey) {
+            tasks.sort_by(|a, b| a.compare(b, key));
+            for t in tasks.iter_mut() {
+                sort(&mut t.subtasks, key);
+            }
+        }
+        sort(&mut self.tasks, key);
+    }
+
+    /// Add a task to the top or bottom of the task list, or of the subtasks of the
+    /// task at `parent`.
+    /// Returns the added task, so it can be given more details.
+    pub fn add_task(
+        &mut self,
+        contents: &str,
+        to: Position,
+        parent: Option<&TaskId>,
+    ) -> Result<&mut Task, Error> {
+        let mut id = match parent {
+            Some(p) => {
+                self.verify_id(p)?;
+                self.operations.push(format!("add '{contents}' to {p}"));
+                p.get_path().to_vec()
+            }
+            None => {
+                self.operations.push(format!("add '{contents}'"));
+                Vec::new()
+            }
+        };
+
+        let (contents, tags) = Self::take_tags(contents);
+        let now = Some(Utc::now());
+        let task = Task {
+            uid: new_uid(),
+            contents,
+            tags,
+            created: now,
+            modified: now,
+            ..Default::default()
+        };
+
+        let siblings = self.get_siblings_mut(&id);
+        let idx = match to {
+            Position::Top => 0,
+            Position::Bottom => siblings.len(),
+        };
+        siblings.insert(idx, task);
+
+        id.push(idx);
+        self.undo_parents(&id);
+        Ok(self.get_mut(&id))
+    }
+
+    /// Replace the notes of the task at `id`, empty notes remove them.
+    pub fn set_notes(&mut self, id: &TaskId, notes: &str) -> Result<(), Error> {
+        self.verify_id(id)?;
+        self.operations.push(format!("note {id}"));
+
+        let task = self.get_mut(id.get_path());
+        task.notes = notes.trim_end().to_owned();
+        task.touch();
+        Ok(())
+    }
+
+    /// Start timing the work on the task at `id`, stopping the timer of any other task.
+    pub fn start_timer(&mut self, id: &TaskId) -> Result<(), Error> {
+        self.verify_id(id)?;
+        if self
+            .get_running()
+            .is_some_and(|(running, _)| running == *id)
+        {
+            return Err(Error::Command(format!("Task {id} is already being timed")));
+        }
+        self.operations.push(format!("start {id}"));
+
+        let now = Utc::now();
+        self.stop_timers(now);
+        let task = self.get_mut(id.get_path());
+        task.intervals.push(Interval {
+            start: now,
+            end: None,
+        });
+        Ok(())
+    }
+
+    /// Stop the running timer, returns the id of the task that was being timed.
+    pub fn stop_timer(&mut self) -> Result<TaskId, Error> {
+        let Some((id, _)) = self.get_running() else {
+            return Err(Error::Command("No task is being timed".to_owned()));
+        };
+        self.operations.push("stop".to_owned());
+        self.stop_timers(Utc::now());
+        Ok(id)
+    }
+
+    /// Returns the task whose timer is running, along with its id.
+    fn get_running(&self) -> Option<(TaskId, &Task)> {
+        self.get_all().into_iter().find(|(_, t)| t.is_running())
+    }
+
+    fn stop_timers(&mut self, now: DateTime<Utc>) {
+        fn stop(tasks: &mut [Task], now: DateTime<Utc>) {
+            for t in tasks.iter_mut() {
+                t.stop_timer(now);
+                stop(&mut t.subtasks, now);
+            }
+        }
+        stop(&mut self.tasks, now);
+    }
+
+    /// Format the time spent on each task and on each day, a running timer counts up to now.
+    pub fn format_time_report(&self) -> String {
+        let now = Utc::now();
+        let mut per_day: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+
+        let timed: Vec<(TaskId, &Task)> = self
+            .get_all()
+            .into_iter()
+            .filter(|(_, t)| !t.intervals.is_empty())
+            .collect();
+        if timed.is_empty() {
+            return "No time tracked".to_owned();
+        }
+
+        let mut out = vec!["Per task:".to_owned()];
+        for (id, t) in timed {
+            let running = if t.is_running() { " (running)" } else { "" };
+            let time = date::format_duration(t.get_tracked_secs());
+            out.push(format!("{id}. {}: {time}{running}", t.contents));
+
+            for i in t.intervals.iter() {
+                // Split intervals at midnight, so each day gets its part.
+                let mut start = i.start.with_timezone(&Local);
+                let end = i.end.unwrap_or(now).with_timezone(&Local);
+                while start < end {
+                    let midnight = (start.date_naive() + Days::new(1))
+                        .and_time(NaiveTime::MIN)
+                        .and_local_timezone(Local)
+                        .earliest();
+                    let until = midnight.map_or(end, |m| m.min(end));
+
+                    *per_day.entry(start.date_naive()).or_default() +=
+                        (until - start).num_seconds();
+                    start = until;
+                }
+            }
+        }
+
+        out.push("\nPer day:".to_owned());
+        for (day, secs) in per_day.iter() {
+            out.push(format!("{day}: {}", date::format_duration(*secs)));
+        }
+        let total = per_day.values().sum();
+        out.push(format!("\nTotal: {}", date::format_duration(total)));
+        out.join("\n")
+    }
+
+    /// Make the tasks `ids` recur, `None` stops them from recurring.
+    pub fn set_recurrence(
+        &mut self,
+        ids: &Selection,
+        recurrence: Option<Recurrence>,
+    ) -> Result<(), Error> {
+        let parsed = self.select(ids)?;
+        let rule = recurrence.map_or("none".to_owned(), |r| r.to_string());
+        self.operations.push(format!("recur {ids} {rule}"));
+
+        for id in parsed {
+            let task = self.get_mut(id.get_path());
+            task.recurrence = recurrence;
+            task.touch();
+        }
+        Ok(())
+    }
+
+    /// Add `tags` to the tasks `ids`, or remove them from the tasks if `add` is false.
+    pub fn tag_tasks(&mut self, ids: &Selection, tags: &[String], add: bool) -> Result<(), Error> {
+        let parsed = self.select(ids)?;
+        let op = if add { "tag" } else { "untag" };
+        let tag_list: Vec<String> = tags.iter().map(|t| format!("+{t}")).collect();
+        self.operations
+            .push(format!("{op} {ids} {}", tag_list.join(" ")));
+
+        for id in parsed {
+            let task = self.get_mut(id.get_path());
+            if add {
+                for tag in tags.iter() {
+                    if !task.tags.contains(tag) {
+                        task.tags.push(tag.to_owned());
+                    }
+                }
+            } else {
+                task.tags.retain(|t| !tags.contains(t));
+            }
+            task.touch();
+        }
+        Ok(())
+    }
+
+    /// Set the priority of the tasks `ids`, `None` removes it.
+    pub fn set_priority(
+        &mut self,
+        ids: &Selection,
+        priority: Option<Priority>,
+    ) -> Result<(), Error> {
+        let parsed = self.select(ids)?;
+        let level = priority.map_or("none", |p| p.get_name());
+        self.operations.push(format!("prio {ids} {level}"));
+
+        for id in parsed {
+            let task = self.get_mut(id.get_path());
+            task.priority = priority;
+            task.touch();
+        }
+        Ok(())
+    }
+
+    /// Set the due date of the tasks `ids`, `None` removes it.
+    pub fn set_due(&mut self, ids: &Selection, due: Option<NaiveDate>) -> Result<(), Error> {
+        let parsed = self.select(ids)?;
+        let when = due.map_or("none".to_owned(), |d| d.to_string());
+        self.operations.push(format!("due {ids} {when}"));
+
+        for id in parsed {
+            let task = self.get_mut(id.get_path());
+            task.due = due;
+            task.touch();
+        }
+        Ok(())
+    }
+
+    pub fn mark_tasks(&mut self, ids: &Selection, done: bool) -> Result<(), Error> {
+        let mut parsed = self.select(ids)?;
+        let op = if done { "do" } else { "undo" };
+        self.operations.push(format!("{op} {ids}"));
+
+        // Marking a task done marks its subtasks too, marking them on their own as well
+        // would complete a recurring task twice.
+        if done {
+            parsed = Self::drop_nested(parsed);
+        }
+
+        // Deepest tasks first, so that marking a task isn't undone by marking its subtasks.
+        parsed.sort_by_key(|i| Reverse(i.get_path().len()));
+
+        for i in parsed {
+            let i = i.get_path();
+            let task = self.get_mut(i);
+            task.set_done(done);
+
+            // If a task is marked done, so will its subtasks.
+            if done {
+                task.mark_subtasks_done();
+                task.recur();
+            }
+
+            // If all the subtasks are done, so will the task.
+            for depth in (1..i.len()).rev() {
+                let parent = self.get_mut(&i[..depth]);
+                let was_done = parent.done;
+                parent.set_done(parent.subtasks.iter().all(|t| t.done));
+                if parent.done && !was_done {
+                    parent.recur();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the tasks `ids` together, in the order they're in, so that the first of them
+    /// ends up at `to`. Subtasks of a moved task move along with it.
+    pub fn move_tasks(&mut self, ids: &Selection, to: &TaskId) -> Result<(), Error> {
+        let parsed = Self::drop_nested(self.select(ids)?);
+        self.verify_destination(to)?;
+        if parsed.iter().any(|id| to.is_inside(id)) {
+            return Err(Error::InvalidId(
+                "A task can't be moved inside itself".to_owned(),
+            ));
+        }
+        self.operations.push(format!("move {ids} {to}"));
+
+        // Removing the tasks shifts the ids after them, so the parent of `to` is found again
+        // by its unique id.
+        let parent_uid = to
+            .get_parent()
+            .map(|p| self.get(p.get_path()).expect("verified").uid.clone());
+        let tasks = self.remove_tasks(&parsed);
+
+        let to_parent = match parent_uid {
+            Some(uid) => {
+                let (id, _) = self
+                    .get_all()
+                    .into_iter()
+                    .find(|(_, t)| t.uid == uid)
+                    .expect("parents of `to` aren't moved");
+                id.get_path().to_vec()
+            }
+            None => Vec::new(),
+        };
+        let siblings = self.get_siblings_mut(&to_parent);
+        let to_idx = to.get_index().min(siblings.len());
+        siblings.splice(to_idx..to_idx, tasks);
+        Ok(())
+    }
+
+    pub fn swap_tasks(&mut self, id1: &TaskId, id2: &TaskId) -> Result<(), Error> {
+        self.verify_id(id1)?;
+        self.verify_id(id2)?;
+        if id1.is_inside(id2) || id2.is_inside(id1) {
+            return Err(Error::InvalidId(
+                "A task can't be swapped with one of its subtasks".to_owned(),
+            ));
+        }
+        self.operations.push(format!("swap {id1} {id2}"));
+
+        let t1 = self.get_mut(id1.get_path()).clone();
+        let t2 = std::mem::replace(self.get_mut(id2.get_path()), t1);
+        *self.get_mut(id1.get_path()) = t2;
+        Ok(())
+    }
+
+    pub fn append_to_tasks(&mut self, ids: &Selection, content: &str) -> Result<(), Error> {
+        let parsed = self.select(ids)?;
+        self.operations.push(format!("append {ids} '{content}'"));
+
+        for id in parsed {
+            let task = self.get_mut(id.get_path());
+            task.contents.push_str(&format!(" {content}"));
+            task.set_done(false);
+            task.touch();
+            self.undo_parents(id.get_path());
+        }
+        Ok(())
+    }
+
+    pub fn edit_tasks(&mut self, ids: &Selection, new_content: &str) -> Result<(), Error> {
+        let parsed = self.select(ids)?;
+        self.operations.push(format!("edit {ids} '{new_content}'"));
+
+        for id in parsed {
+            let task = self.get_mut(id.get_path());
+            task.contents = new_content.to_owned();
+            task.set_done(false);
+            task.touch();
+            self.undo_parents(id.get_path());
+        }
+        Ok(())
+    }
+
+    /// Delete the tasks `ids` along with their subtasks.
+    pub fn delete_tasks(&mut self, ids: &Selection) -> Result<(), Error> {
+        let parsed = Self::drop_nested(self.select(ids)?);
+        self.operations.push(format!("delete {ids}"));
+        self.remove_tasks(&parsed);
+        Ok(())
+    }
+
+    /// Remove the tasks `ids` and return them in the same order. `ids` must be sorted, with
+    /// none inside another.
+    fn remove_tasks(&mut self, ids: &[TaskId]) -> Vec<Task> {
+        // Last first, so the ids before it still point to the same tasks.
+        let mut tasks: Vec<Task> = ids
+            .iter()
+            .rev()
+            .map(|id| {
+                let (idx, parent) = id.get_path().split_last().expect("ids aren't empty");
+                self.get_siblings_mut(parent).remove(*idx)
+            })
+            .collect();
+        tasks.reverse();
+        tasks
+    }
+
+    /// Drop the ids of subtasks whose parent is in `ids` too, since they go along with it.
+    fn drop_nested(ids: Vec<TaskId>) -> Vec<TaskId> {
+        ids.iter()
+            .filter(|id| !ids.iter().any(|p| id.is_inside(p)))
+            .cloned()
+            .collect()
+    }
+
+    /// Move the done tasks to the archive, recurring tasks are kept.
+    pub fn clear_dones(&mut self) {
+        self.operations.push("clear".to_owned());
+
+        fn clear(tasks: &mut Vec<Task>, parent: &str, archive: &mut Vec<ArchivedTask>) {
+            // Done tasks holding a recurring subtask stay along with it.
+            for mut task in tasks.extract_if(.., |t| t.done && !t.has_recurrence()) {
+                // Tasks done before completion times were recorded.
+                task.completed.get_or_insert_with(Utc::now);
+                archive.push(ArchivedTask {
+                    task,
+                    parent: parent.to_owned(),
+                });
+            }
+            for t in tasks.iter_mut() {
+                clear(&mut t.subtasks, &t.uid, archive);
+            }
+        }
+        clear(&mut self.tasks, "", &mut self.archive);
+    }
+
+    /// Format the archived tasks containing `pattern`, case insensitive, along with
+    /// their ids in the archive. An empty pattern formats all of them.
+    pub fn format_archive(&self, pattern: &str, colored: bool) -> String {
+        if self.archive.is_empty() {
+            return "The archive is empty".to_owned();
+        }
+        let pattern = pattern.to_lowercase();
+        let matches = |t: &Task| {
+            fn find(t: &Task, pattern: &str) -> bool {
+                t.contents.to_lowercase().contains(pattern)
+                    || t.subtasks.iter().any(|st| find(st, pattern))
+            }
+            find(t, &pattern)
+        };
+
+        let mut stack: Vec<(String, &Task)> = self
+            .archive
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| matches(&a.task))
+            .map(|(i, a)| ((i + 1).to_string(), &a.task))
+            .rev()
+            .collect();
+        if stack.is_empty() {
+            return "No archived tasks match".to_owned();
+        }
+
+        let mut out: Vec<String> = Vec::new();
+        while let Some((id, t)) = stack.pop() {
+            let depth = id.matches('.').count();
+            let mut cont = t.contents.clone();
+            if let Some(c) = t.completed {
+                cont.push_str(&format!(" (done {})", c.with_timezone(&Local).date_naive()));
+            }
+
+            let indent = "\t".repeat(depth);
+            if colored {
+                out.push(format!("{indent}{id}. \x1b[0;32m[X] {cont} \x1b[0m"));
+            } else {
+                out.push(format!("{indent}{id}. [X] {cont}"));
+            }
+            let subtasks = t.subtasks.iter().enumerate().rev();
+            stack.extend(subtasks.map(|(i, st)| (format!("{id}.{}", i + 1), st)));
+        }
+        out.join("\n")
+    }
+
+    /// Move the task at `id` in the archive back to its parent, or to the bottom of the
+    /// task list when its parent no longer exists.
+    pub fn unarchive(&mut self, id: usize) -> Result<(), Error> {
+        if id == 0 || id > self.archive.len() {
+            return Err(Error::OutOfBounds(format!(
+                "Archived task `{id}` doesn't exist"
+            )));
+        }
+        self.operations.push(format!("unarchive {id}"));
+
+        let archived = self.archive.remove(id - 1);
+        let parent = self
+            .get_all()
+            .into_iter()
+            .find(|(_, t)| !archived.parent.is_empty() && t.uid == archived.parent)
+            .map(|(id, _)| id);
+        match parent {
+            Some(parent) => self.get_mut(parent.get_path()).subtasks.push(archived.task),
+            None => self.tasks.push(archived.task),
+        }
+        Ok(())
+    }
+}
+
+impl Priority {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
         }
     }
 
-    // I've not found a way to automatically test `print` so for now it's ignored by default
-    // and requires manual testing, or just do `cargo r`
+    /// Printed before the task's contents.
+    fn get_mark(&self) -> &'static str {
+        match self {
+            Priority::Low => "!",
+            Priority::Medium => "!!",
+            Priority::High => "!!!",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = Error;
+
+    /// Accepts `high`, `medium`, `low` or their first letter.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "h" | "high" => Ok(Priority::High),
+            "m" | "med" | "medium" => Ok(Priority::Medium),
+            "l" | "low" => Ok(Priority::Low),
+            _ => Err(Error::InvalidOption(format!("Invalid priority `{s}`"))),
+        }
+    }
+}
+
+impl FromStr for Position {
+    type Err = Error;
+
+    /// Accepts `top`, `bottom` or `bot`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top" => Ok(Position::Top),
+            "bot" | "bottom" => Ok(Position::Bottom),
+            _ => Err(Error::InvalidOption(format!("Invalid position `{s}`"))),
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    /// Accepts `due` or `priority`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "due" => Ok(SortKey::Due),
+            "priority" => Ok(SortKey::Priority),
+            _ => Err(Error::InvalidOption(format!("Invalid sort key `{s}`"))),
+        }
+    }
+}
+
+impl SortKey {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            SortKey::Due => "due",
+            SortKey::Priority => "priority",
+        }
+    }
+}
+
+/// Generate a random id for a new task.
+fn new_uid() -> String {
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    SystemTime::now().hash(&mut hasher);
+    COUNT
+        .fetch_add(1, atomic::Ordering::Relaxed)
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+impl Task {
+    pub fn get_uid(&self) -> &str {
+        &self.uid
+    }
+
+    pub fn get_contents(&self) -> &str {
+        &self.contents
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn get_due(&self) -> Option<NaiveDate> {
+        self.due
+    }
+
+    pub fn set_due(&mut self, due: Option<NaiveDate>) {
+        self.due = due;
+    }
+
+    pub fn get_priority(&self) -> Option<Priority> {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, priority: Option<Priority>) {
+        self.priority = priority;
+    }
+
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn get_notes(&self) -> &str {
+        &self.notes
+    }
+
+    /// Compare everything but the unique id, contents, state and subtasks of the tasks.
+    pub fn same_details(&self, other: &Task) -> bool {
+        self.due == other.due
+            && self.priority == other.priority
+            && self.tags == other.tags
+            && self.notes == other.notes
+            && self.recurrence == other.recurrence
+            && self.completions == other.completions
+            && self.intervals == other.intervals
+    }
+
+    /// Order of the tasks when sorted by `key`, tasks without a value for it go last.
+    fn compare(&self, other: &Task, key: SortKey) -> Ordering {
+        match key {
+            SortKey::Due => (self.due.is_none(), self.due).cmp(&(other.due.is_none(), other.due)),
+            SortKey::Priority => (self.priority.is_none(), Reverse(self.priority))
+                .cmp(&(other.priority.is_none(), Reverse(other.priority))),
+        }
+    }
+
+    /// Whether `print` shows the task, either it or one of its subtasks passes the filters.
+    fn is_shown(&self, opts: &PrintOptions) -> bool {
+        self.passes_filters(opts)
+            || !opts.top_level_only && self.subtasks.iter().any(|t| t.is_shown(opts))
+    }
+
+    fn passes_filters(&self, opts: &PrintOptions) -> bool {
+        // Results of the filters in use.
+        let mut results: Vec<bool> = Vec::new();
+        if let Some(before) = opts.due_before {
+            results.push(self.due.is_some_and(|d| d <= before));
+        }
+        if let Some(min) = opts.min_priority {
+            results.push(self.priority.is_some_and(|p| p >= min));
+        }
+        for t in opts.tags.iter() {
+            results.push(self.tags.contains(t));
+        }
+        for (time, since) in [
+            (self.completed, opts.done_since),
+            (self.created, opts.created_since),
+            (self.modified, opts.modified_since),
+        ] {
+            if since.is_some() {
+                results.push(Self::is_since(time, since));
+            }
+        }
+        if opts.pending {
+            results.push(!self.done);
+        }
+        if opts.done {
+            results.push(self.done);
+        }
+        if let Some(pattern) = &opts.pattern {
+            results.push(pattern.is_match(&self.contents));
+        }
+
+        if opts.any && !results.is_empty() {
+            results.contains(&true)
+        } else {
+            !results.contains(&false)
+        }
+    }
+
+    fn mark_subtasks_done(&mut self) {
+        for t in self.subtasks.iter_mut() {
+            t.set_done(true);
+            t.mark_subtasks_done();
+            // Recurring subtasks come back, as when they're marked done on their own.
+            t.recur();
+        }
+    }
+
+    /// Whether the task or one of its subtasks recurs.
+    fn has_recurrence(&self) -> bool {
+        self.recurrence.is_some() || self.subtasks.iter().any(Task::has_recurrence)
+    }
+
+    pub fn set_recurrence(&mut self, recurrence: Option<Recurrence>) {
+        self.recurrence = recurrence;
+    }
+
+    /// If the task recurs and is done, record its completion and bring it back undone,
+    /// along with its subtasks, due on its next occurrence.
+    fn recur(&mut self) {
+        let Some(rule) = self.recurrence.filter(|_| self.done) else {
+            return;
+        };
+        self.completions
+            .push(self.completed.unwrap_or_else(Utc::now));
+        self.due = Some(rule.next(self.due, date::today()));
+
+        fn reset(t: &mut Task) {
+            t.set_done(false);
+            t.subtasks.iter_mut().for_each(reset);
+        }
+        reset(self);
+    }
+
+    /// Whether `time` is on or after the local date `since`, a missing `since` always passes.
+    fn is_since(time: Option<DateTime<Utc>>, since: Option<NaiveDate>) -> bool {
+        since
+            .is_none_or(|since| time.is_some_and(|t| t.with_timezone(&Local).date_naive() >= since))
+    }
+
+    fn is_running(&self) -> bool {
+        self.intervals.last().is_some_and(|i| i.end.is_none())
+    }
+
+    /// Seconds spent on the task, a running timer counts up to now.
+    pub fn get_tracked_secs(&self) -> i64 {
+        let now = Utc::now();
+        self.intervals
+            .iter()
+            .map(|i| (i.end.unwrap_or(now) - i.start).num_seconds())
+            .sum()
+    }
+
+    fn stop_timer(&mut self, now: DateTime<Utc>) {
+        if let Some(i) = self.intervals.last_mut().filter(|i| i.end.is_none()) {
+            i.end = Some(now);
+        }
+    }
+
+    /// Mark the task, recording when it was completed.
+    /// Its timer is stopped when it's done.
+    fn set_done(&mut self, done: bool) {
+        if done && !self.done {
+            self.completed = Some(Utc::now());
+            self.stop_timer(Utc::now());
+        } else if !done {
+            self.completed = None;
+        }
+        self.done = done;
+    }
+
+    /// Record that the task was changed.
+    fn touch(&mut self) {
+        self.modified = Some(Utc::now());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process;
+
+    fn get_id(id: &str) -> TaskId {
+        id.parse().unwrap()
+    }
+
+    fn get_ids(ids: &str) -> Selection {
+        ids.parse().unwrap()
+    }
+
+    fn get_test_task_file() -> TaskFile {
+        TaskFile {
+            task_file_path: PathBuf::new(),
+            tasks: vec![
+                Task {
+                    uid: "a001".to_owned(),
+                    contents: "one".to_owned(),
+                    done: false,
+                    subtasks: vec![
+                        Task {
+                            uid: "a002".to_owned(),
+                            contents: "one".to_owned(),
+                            done: false,
+                            subtasks: vec![],
+                            ..Default::default()
+                        },
+                        Task {
+                            uid: "a003".to_owned(),
+                            contents: "two".to_owned(),
+                            done: false,
+                            subtasks: vec![],
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                Task {
+                    uid: "a004".to_owned(),
+                    contents: "two".to_owned(),
+                    done: false,
+                    subtasks: vec![
+                        Task {
+                            uid: "a005".to_owned(),
+                            contents: "one".to_owned(),
+                            done: false,
+                            subtasks: vec![],
+                            ..Default::default()
+                        },
+                        Task {
+                            uid: "a006".to_owned(),
+                            contents: "two".to_owned(),
+                            done: false,
+                            subtasks: vec![],
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn get_done_test_task_file() -> TaskFile {
+        TaskFile {
+            task_file_path: PathBuf::new(),
+            tasks: vec![
+                Task {
+                    uid: "a007".to_owned(),
+                    contents: "one".to_owned(),
+                    done: true,
+                    subtasks: vec![
+                        Task {
+                            uid: "a008".to_owned(),
+                            contents: "one".to_owned(),
+                            done: true,
+                            subtasks: vec![],
+                            ..Default::default()
+                        },
+                        Task {
+                            uid: "a009".to_owned(),
+                            contents: "two".to_owned(),
+                            done: true,
+                            subtasks: vec![],
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                Task {
+                    uid: "a00a".to_owned(),
+                    contents: "two".to_owned(),
+                    done: true,
+                    subtasks: vec![
+                        Task {
+                            uid: "a00b".to_owned(),
+                            contents: "one".to_owned(),
+                            done: true,
+                            subtasks: vec![],
+                            ..Default::default()
+                        },
+                        Task {
+                            uid: "a00c".to_owned(),
+                            contents: "two".to_owned(),
+                            done: true,
+                            subtasks: vec![],
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Returns an empty directory that only the test `name` uses.
+    fn get_test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tsk_test_{name}_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // I've not found a way to automatically test `print` so for now it's ignored by default
+    // and requires manual testing, or just do `cargo r`
+    #[test]
+    #[ignore = "Manually view the output of this test"]
+    fn test_print() {
+        let tf = get_test_task_file();
+        println!(
+            "{}",
+            tf.format_tasks(&PrintOptions {
+                show_uids: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    // ADDING TASKS
+    #[test]
+    fn test_add_task_top() {
+        let mut tf = get_test_task_file();
+        tf.add_task("three", Position::Top, None).unwrap();
+
+        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
+        assert_eq!(v, ["three", "one", "two"]);
+    }
+
+    #[test]
+    fn test_parse_position_and_sort_key() {
+        assert_eq!("bottom".parse(), Ok(Position::Bottom));
+        assert_eq!("priority".parse(), Ok(SortKey::Priority));
+        assert!(matches!(
+            "middle".parse::<Position>(),
+            Err(Error::InvalidOption(_))
+        ));
+        assert!(matches!(
+            "size".parse::<SortKey>(),
+            Err(Error::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_task_bottom() {
+        let mut tf = get_test_task_file();
+        tf.add_task("three", "bot".parse().unwrap(), None).unwrap();
+        tf.add_task("four", Position::Bottom, None).unwrap();
+
+        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
+        assert_eq!(v, ["one", "two", "three", "four"]);
+    }
+
+    #[test]
+    fn test_add_sub_top() {
+        let mut tf = get_test_task_file();
+        tf.add_task("sub", Position::Top, Some(&get_id("2")))
+            .unwrap();
+
+        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
+        let sv0: Vec<&str> = tf.tasks[0]
+            .subtasks
+            .iter()
+            .map(|t| t.contents.as_str())
+            .collect();
+        let sv1: Vec<&str> = tf.tasks[1]
+            .subtasks
+            .iter()
+            .map(|t| t.contents.as_str())
+            .collect();
+
+        assert_eq!(v, ["one", "two"]);
+        assert_eq!(sv0, ["one", "two"]);
+        assert_eq!(sv1, ["sub", "one", "two"]);
+    }
+
+    #[test]
+    fn test_add_sub_bottom() {
+        let mut tf = get_test_task_file();
+        tf.add_task("sub3", "bot".parse().unwrap(), Some(&get_id("2")))
+            .unwrap();
+        tf.add_task("sub4", Position::Bottom, Some(&get_id("2")))
+            .unwrap();
+
+        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
+        let sv0: Vec<&str> = tf.tasks[0]
+            .subtasks
+            .iter()
+            .map(|t| t.contents.as_str())
+            .collect();
+        let sv1: Vec<&str> = tf.tasks[1]
+            .subtasks
+            .iter()
+            .map(|t| t.contents.as_str())
+            .collect();
+
+        assert_eq!(v, ["one", "two"]);
+        assert_eq!(sv0, ["one", "two"]);
+        assert_eq!(sv1, ["one", "two", "sub3", "sub4"]);
+    }
+
+    #[test]
+    fn test_adding_sub_undoes_task() {
+        let mut tf = get_done_test_task_file();
+
+        assert!(tf.tasks[1].done);
+        tf.add_task("sub", Position::Top, Some(&get_id("2")))
+            .unwrap();
+        assert!(!tf.tasks[1].done);
+    }
+
+    #[test]
+    fn test_add_nested_subtask() {
+        let mut tf = get_done_test_task_file();
+        tf.add_task("deep", Position::Bottom, Some(&get_id("2.1")))
+            .unwrap();
+        tf.add_task("deeper", Position::Bottom, Some(&get_id("2.1.1")))
+            .unwrap();
+
+        assert_eq!(tf.tasks[1].subtasks[0].subtasks[0].contents, "deep");
+        assert_eq!(
+            tf.tasks[1].subtasks[0].subtasks[0].subtasks[0].contents,
+            "deeper"
+        );
+
+        // Every parent now has a task that isn't done.
+        assert!(!tf.tasks[1].done);
+        assert!(!tf.tasks[1].subtasks[0].done);
+        assert!(!tf.tasks[1].subtasks[0].subtasks[0].done);
+        assert!(tf.tasks[1].subtasks[1].done);
+    }
+
+    // MARKING TASKS
+    #[test]
+    fn test_mark_tasks() {
+        let mut tf = get_test_task_file();
+        tf.mark_tasks(&get_ids("1"), true).unwrap();
+        tf.mark_tasks(&get_ids("2"), true).unwrap();
+
+        assert!(tf.tasks[0].done);
+        assert!(tf.tasks[1].done);
+
+        tf.mark_tasks(&get_ids("1"), false).unwrap();
+        tf.mark_tasks(&get_ids("2"), false).unwrap();
+
+        assert!(!tf.tasks[0].done);
+        assert!(!tf.tasks[1].done);
+    }
+
+    #[test]
+    fn test_mark_sub() {
+        let mut tf = get_test_task_file();
+        tf.mark_tasks(&get_ids("1.1"), true).unwrap();
+        assert!(!tf.tasks[0].done);
+        assert!(tf.tasks[0].subtasks[0].done);
+
+        tf.mark_tasks(&get_ids("1.2"), true).unwrap();
+        assert!(tf.tasks[0].done);
+        assert!(tf.tasks[0].subtasks[1].done);
+        assert!(!tf.tasks[1].done);
+
+        tf.mark_tasks(&get_ids("1.1"), false).unwrap();
+        assert!(!tf.tasks[0].done);
+        assert!(!tf.tasks[0].subtasks[0].done);
+
+        tf.mark_tasks(&get_ids("1.2"), false).unwrap();
+        assert!(!tf.tasks[0].subtasks[1].done);
+    }
+
+    #[test]
+    fn test_marking_task_done_marks_subtasks() {
+        let mut tf = get_test_task_file();
+
+        tf.mark_tasks(&get_ids("2"), true).unwrap();
+        assert!(!tf.tasks[0].done);
+        assert!(tf.tasks[1].done);
+        assert!(tf.tasks[1].subtasks[0].done);
+        assert!(tf.tasks[1].subtasks[1].done);
+
+        tf.mark_tasks(&get_ids("2"), false).unwrap();
+        assert!(!tf.tasks[1].done);
+        assert!(tf.tasks[1].subtasks[0].done);
+        assert!(tf.tasks[1].subtasks[1].done);
+    }
+
+    #[test]
+    fn test_mark_nested_subtasks() {
+        let mut tf = get_test_task_file();
+        tf.add_task("deep", Position::Bottom, Some(&get_id("1.1")))
+            .unwrap();
+        tf.add_task("deeper", Position::Bottom, Some(&get_id("1.1")))
+            .unwrap();
+
+        tf.mark_tasks(&get_ids("1.1.1"), true).unwrap();
+        assert!(!tf.tasks[0].subtasks[0].done);
+
+        tf.mark_tasks(&get_ids("1.1.2,1.2"), true).unwrap();
+        assert!(tf.tasks[0].subtasks[0].done);
+        assert!(tf.tasks[0].done);
+
+        tf.mark_tasks(&get_ids("1.1.1"), false).unwrap();
+        assert!(!tf.tasks[0].subtasks[0].done);
+        assert!(!tf.tasks[0].done);
+
+        tf.mark_tasks(&get_ids("1"), true).unwrap();
+        assert!(tf.tasks[0].subtasks[0].subtasks[0].done);
+        assert!(tf.tasks[0].subtasks[0].subtasks[1].done);
+    }
+
+    // MOVING TASKS
+    #[test]
+    fn test_move_task() {
+        let mut tf = get_test_task_file();
+        tf.add_task("other", Position::Bottom, None).unwrap();
+        tf.add_task("more", Position::Bottom, None).unwrap();
+        tf.move_tasks(&get_ids("3"), &get_id("1")).unwrap();
+
+        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
+        assert_eq!(v, ["other", "one", "two", "more"]);
+
+        tf.move_tasks(&get_ids("1"), &get_id("3")).unwrap();
+
+        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
+        assert_eq!(v, ["one", "two", "other", "more"]);
+    }
+
+    #[test]
+    fn test_move_subtask() {
+        let mut tf = get_test_task_file();
+        tf.add_task("other", Position::Top, Some(&get_id("1")))
+            .unwrap();
+        tf.move_tasks(&get_ids("1.1"), &get_id("1.3")).unwrap();
+
+        let sv0: Vec<&str> = tf.tasks[0]
+            .subtasks
+            .iter()
+            .map(|t| t.contents.as_str())
+            .collect();
+        assert_eq!(sv0, ["one", "two", "other"]);
+
+        tf.move_tasks(&get_ids("1.3"), &get_id("1.1")).unwrap();
+        let sv0: Vec<&str> = tf.tasks[0]
+            .subtasks
+            .iter()
+            .map(|t| t.contents.as_str())
+            .collect();
+        assert_eq!(sv0, ["other", "one", "two"]);
+    }
+
+    #[test]
+    fn test_move_task_to_subtask_vice_versa() {
+        let mut tf = get_test_task_file();
+        tf.add_task("other", Position::Top, Some(&get_id("1")))
+            .unwrap();
+        tf.move_tasks(&get_ids("1.1"), &get_id("3")).unwrap();
+
+        assert_eq!(tf.tasks[0].contents, "one");
+        assert_eq!(tf.tasks[0].subtasks[0].contents, "one");
+        assert_eq!(tf.tasks[0].subtasks[1].contents, "two");
+        assert_eq!(tf.tasks[1].contents, "two");
+        assert_eq!(tf.tasks[2].contents, "other");
+
+        tf.move_tasks(&get_ids("1"), &get_id("3.1")).unwrap();
+        assert_eq!(tf.tasks[0].contents, "two");
+        assert_eq!(tf.tasks[1].contents, "other");
+        assert_eq!(tf.tasks[1].subtasks[0].contents, "one");
+
+        tf.move_tasks(&get_ids("2"), &get_id("1.1")).unwrap();
+        assert_eq!(tf.tasks[0].contents, "two");
+        assert_eq!(tf.tasks[0].subtasks[0].contents, "other");
+        assert_eq!(tf.tasks[0].subtasks[1].contents, "one");
+        assert_eq!(tf.tasks[0].subtasks[2].contents, "two");
+    }
+
+    #[test]
+    fn test_move_nested_task() {
+        let mut tf = get_test_task_file();
+        tf.add_task("deep", Position::Bottom, Some(&get_id("2.2")))
+            .unwrap();
+
+        tf.move_tasks(&get_ids("2.2.1"), &get_id("1.1.1")).unwrap();
+        assert!(tf.tasks[1].subtasks[1].subtasks.is_empty());
+        assert_eq!(tf.tasks[0].subtasks[0].subtasks[0].contents, "deep");
+
+        // Moving a task takes its subtasks along.
+        tf.move_tasks(&get_ids("1.1"), &get_id("2.1.1")).unwrap();
+        assert_eq!(tf.tasks[0].subtasks.len(), 1);
+        assert_eq!(tf.tasks[1].subtasks[0].subtasks[0].contents, "one");
+        assert_eq!(
+            tf.tasks[1].subtasks[0].subtasks[0].subtasks[0].contents,
+            "deep"
+        );
+
+        // The parent's position shifts once the moved task is removed.
+        tf.move_tasks(&get_ids("1"), &get_id("2.1.1")).unwrap();
+        assert_eq!(tf.tasks.len(), 1);
+        assert_eq!(tf.tasks[0].subtasks[0].subtasks[0].contents, "one");
+    }
+
+    #[test]
+    fn test_move_tasks_together() {
+        let mut tf = get_test_task_file();
+        tf.add_task("other", Position::Bottom, None).unwrap();
+        tf.add_task("more", Position::Bottom, None).unwrap();
+        let contents = |tf: &TaskFile| -> Vec<String> {
+            tf.tasks.iter().map(|t| t.contents.clone()).collect()
+        };
+
+        // The first moved task ends up at the destination, the rest follow in order.
+        tf.move_tasks(&get_ids("4,1"), &get_id("2")).unwrap();
+        assert_eq!(contents(&tf), ["two", "one", "more", "other"]);
+
+        tf.move_tasks(&get_ids("2..3"), &get_id("1")).unwrap();
+        assert_eq!(contents(&tf), ["one", "more", "two", "other"]);
+
+        // The destination's parent is where it was before the tasks were moved.
+        tf.move_tasks(&get_ids("1,2"), &get_id("3.2")).unwrap();
+        assert_eq!(contents(&tf), ["two", "other"]);
+        let sub: Vec<&str> = tf.tasks[0]
+            .subtasks
+            .iter()
+            .map(|t| t.contents.as_str())
+            .collect();
+        assert_eq!(sub, ["one", "one", "more", "two"]);
+
+        // Subtasks of a moved task move along with it.
+        tf.move_tasks(&get_ids("1.2,1.2.*,2"), &get_id("1"))
+            .unwrap();
+        assert_eq!(contents(&tf), ["one", "other", "two"]);
+        assert_eq!(tf.tasks[0].subtasks.len(), 2);
+
+        assert!(tf.move_tasks(&get_ids("1,3"), &get_id("3.1")).is_err());
+    }
+
+    // SWAPPING TASKS
+    #[test]
+    fn test_swap_tasks() {
+        let mut tf = get_test_task_file();
+        tf.add_task("other", Position::Top, None).unwrap();
+        tf.swap_tasks(&get_id("1"), &get_id("3")).unwrap();
+
+        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
+        assert_eq!(v, ["two", "one", "other"]);
+    }
+
+    #[test]
+    fn test_swap_subtasks() {
+        let mut tf = get_test_task_file();
+        tf.add_task("other", Position::Top, Some(&get_id("1")))
+            .unwrap();
+        tf.swap_tasks(&get_id("1.1"), &get_id("1.3")).unwrap();
+
+        let sv0: Vec<&str> = tf.tasks[0]
+            .subtasks
+            .iter()
+            .map(|t| t.contents.as_str())
+            .collect();
+        assert_eq!(sv0, ["two", "one", "other"]);
+    }
+
+    #[test]
+    fn test_swap_task_subtask() {
+        let mut tf = get_test_task_file();
+        tf.add_task("other", Position::Top, None).unwrap();
+        tf.swap_tasks(&get_id("1"), &get_id("2.2")).unwrap();
+
+        assert_eq!(tf.tasks[0].contents, "two");
+        assert_eq!(tf.tasks[1].subtasks[1].contents, "other");
+
+        tf.swap_tasks(&get_id("2.2"), &get_id("1")).unwrap();
+        assert_eq!(tf.tasks[0].contents, "other");
+        assert_eq!(tf.tasks[1].subtasks[1].contents, "two");
+    }
+
+    #[test]
+    fn test_swap_nested_tasks() {
+        let mut tf = get_test_task_file();
+        tf.add_task("deep", Position::Bottom, Some(&get_id("1.2")))
+            .unwrap();
+        tf.swap_tasks(&get_id("1.2.1"), &get_id("2")).unwrap();
+
+        assert_eq!(tf.tasks[1].contents, "deep");
+        assert_eq!(tf.tasks[0].subtasks[1].subtasks[0].contents, "two");
+        assert_eq!(tf.tasks[0].subtasks[1].subtasks[0].subtasks.len(), 2);
+    }
+
+    // APPENDING TASK
+    #[test]
+    fn test_append_task() {
+        let mut tf = get_test_task_file();
+        tf.append_to_tasks(&get_ids("1"), "new").unwrap();
+        tf.append_to_tasks(&get_ids("1.1"), "new").unwrap();
+
+        assert_eq!(tf.tasks[0].contents, "one new");
+        assert_eq!(tf.tasks[0].subtasks[0].contents, "one new");
+    }
+
+    #[test]
+    fn test_append_task_undo_done() {
+        let mut tf = get_done_test_task_file();
+        tf.append_to_tasks(&get_ids("1"), "new").unwrap();
+        assert!(!tf.tasks[0].done);
+
+        tf = get_done_test_task_file();
+        tf.append_to_tasks(&get_ids("1.1"), "new").unwrap();
+
+        assert!(!tf.tasks[0].done);
+        assert!(!tf.tasks[0].subtasks[0].done);
+    }
+
+    // EDITING TASK
+    #[test]
+    fn test_edit_task() {
+        let mut tf = get_test_task_file();
+        tf.edit_tasks(&get_ids("1"), "new").unwrap();
+        tf.edit_tasks(&get_ids("1.1"), "newer").unwrap();
+
+        assert_eq!(tf.tasks[0].contents, "new");
+        assert_eq!(tf.tasks[0].subtasks[0].contents, "newer");
+    }
+
+    #[test]
+    fn test_edit_tasks() {
+        let mut tf = get_test_task_file();
+        tf.edit_tasks(&get_ids("2.*"), "new").unwrap();
+        tf.append_to_tasks(&get_ids("1.2..2.1"), "more").unwrap();
+
+        assert_eq!(tf.tasks[0].subtasks[1].contents, "two more");
+        assert_eq!(tf.tasks[1].contents, "two");
+        assert_eq!(tf.tasks[1].subtasks[0].contents, "new more");
+        assert_eq!(tf.tasks[1].subtasks[1].contents, "new");
+    }
+
+    #[test]
+    fn test_edit_task_undo_done() {
+        let mut tf = get_done_test_task_file();
+        tf.edit_tasks(&get_ids("1"), "new").unwrap();
+        assert!(!tf.tasks[0].done);
+
+        tf = get_done_test_task_file();
+        tf.edit_tasks(&get_ids("1.1"), "new").unwrap();
+
+        assert!(!tf.tasks[0].done);
+        assert!(!tf.tasks[0].subtasks[0].done);
+    }
+
+    #[test]
+    fn test_edit_nested_task_undo_done() {
+        let mut tf = get_done_test_task_file();
+        tf.add_task("deep", Position::Bottom, Some(&get_id("1.1")))
+            .unwrap();
+        tf.mark_tasks(&get_ids("1"), true).unwrap();
+
+        tf.edit_tasks(&get_ids("1.1.1"), "new").unwrap();
+        assert_eq!(tf.tasks[0].subtasks[0].subtasks[0].contents, "new");
+        assert!(!tf.tasks[0].subtasks[0].done);
+        assert!(!tf.tasks[0].done);
+        assert!(tf.tasks[0].subtasks[1].done);
+    }
+
+    // DELETING TASK
+    #[test]
+    fn test_delete_task() {
+        let mut tf = get_test_task_file();
+        tf.delete_tasks(&get_ids("2.2")).unwrap();
+        tf.delete_tasks(&get_ids("1")).unwrap();
+
+        assert_eq!(tf.tasks.len(), 1);
+        assert_eq!(tf.tasks[0].subtasks.len(), 1)
+    }
+
+    #[test]
+    fn test_delete_tasks() {
+        let mut tf = get_test_task_file();
+        tf.add_task("other", Position::Bottom, None).unwrap();
+        tf.add_task("more", Position::Bottom, None).unwrap();
+
+        // Ids refer to the tasks as they were before deleting any.
+        tf.delete_tasks(&get_ids("1.1,2..3")).unwrap();
+        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
+        assert_eq!(v, ["one", "more"]);
+        assert_eq!(tf.tasks[0].subtasks.len(), 1);
+
+        // Subtasks of a deleted task are deleted along with it.
+        tf.delete_tasks(&get_ids("1,1.1")).unwrap();
+        assert_eq!(tf.tasks.len(), 1);
+        assert_eq!(tf.tasks[0].contents, "more");
+
+        assert!(tf.delete_tasks(&get_ids("1,2")).is_err());
+        assert_eq!(tf.tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_clear_dones() {
+        let mut tf = get_test_task_file();
+        tf.tasks[1].subtasks[0].done = true;
+        tf.tasks[0].done = true;
+        tf.clear_dones();
+
+        assert_eq!(tf.tasks.len(), 1);
+        assert_eq!(tf.tasks[0].subtasks.len(), 1);
+    }
+
+    #[test]
+    fn test_clear_nested_dones() {
+        let mut tf = get_test_task_file();
+        tf.add_task("deep", Position::Bottom, Some(&get_id("1.1")))
+            .unwrap();
+        tf.add_task("deeper", Position::Bottom, Some(&get_id("1.1")))
+            .unwrap();
+        tf.mark_tasks(&get_ids("1.1.2"), true).unwrap();
+        tf.clear_dones();
+
+        assert_eq!(tf.tasks[0].subtasks[0].subtasks.len(), 1);
+        assert_eq!(tf.tasks[0].subtasks[0].subtasks[0].contents, "deep");
+    }
+
+    // FIND
+    #[test]
+    fn test_get_matches() {
+        let mut tf = get_test_task_file();
+        tf.edit_tasks(&get_ids("2.2"), "release notes").unwrap();
+
+        let matches = tf.get_matches(&Regex::new("two").unwrap());
+        let ids: Vec<String> = matches.iter().map(|(id, _)| id.to_string()).collect();
+        assert_eq!(ids, ["1.2", "2"]);
+
+        let matches = tf.get_matches(&Regex::new("^release").unwrap());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0, get_id("2.2"));
+
+        assert!(tf.get_matches(&Regex::new("three").unwrap()).is_empty());
+    }
+
+    // ARCHIVE
+    #[test]
+    fn test_clear_archives_dones() {
+        let mut tf = get_test_task_file();
+        tf.mark_tasks(&get_ids("2.1"), true).unwrap();
+        tf.tasks[0].done = true;
+        tf.clear_dones();
+
+        assert_eq!(tf.archive.len(), 2);
+        assert_eq!(tf.archive[0].task.uid, "a001");
+        assert_eq!(tf.archive[0].task.subtasks.len(), 2);
+        assert!(tf.archive[0].parent.is_empty());
+        assert!(tf.archive[0].task.completed.is_some());
+        assert_eq!(tf.archive[1].task.uid, "a005");
+        assert_eq!(tf.archive[1].parent, "a004");
+    }
+
+    #[test]
+    fn test_unarchive() {
+        let mut tf = get_test_task_file();
+        tf.mark_tasks(&get_ids("2.1"), true).unwrap();
+        tf.tasks[0].done = true;
+        tf.clear_dones();
+
+        tf.unarchive(2).unwrap();
+        assert_eq!(tf.tasks[0].subtasks.len(), 2);
+        assert_eq!(tf.tasks[0].subtasks[1].uid, "a005");
+
+        // Its parent is gone, so it goes back to the top level.
+        tf.archive[0].parent = "gone".to_owned();
+        tf.unarchive(1).unwrap();
+        assert_eq!(tf.tasks.len(), 2);
+        assert_eq!(tf.tasks[1].uid, "a001");
+
+        assert!(tf.archive.is_empty());
+        assert!(tf.unarchive(1).is_err());
+    }
+
+    // TASK LISTS
+    #[test]
+    fn test_list_names() {
+        assert!(TaskFile::is_valid_list_name("release"));
+        assert!(TaskFile::is_valid_list_name("old_release-2"));
+        assert!(!TaskFile::is_valid_list_name(""));
+        assert!(!TaskFile::is_valid_list_name("a.b"));
+        assert!(!TaskFile::is_valid_list_name("a/b"));
+    }
+
+    #[test]
+    fn test_list_management() {
+        let dir = get_test_dir("lists");
+        File::create(dir.join("tasks.json")).unwrap();
+        File::create(dir.join("tasks.json.bak")).unwrap();
+
+        assert!(TaskFile::create_list(&dir, "release").is_ok());
+        assert!(TaskFile::create_list(&dir, "release").is_err());
+        assert_eq!(TaskFile::get_lists(&dir), ["release", "tasks"]);
+        File::create(dir.join("release.json.lock")).unwrap();
+
+        File::create(dir.join("release.json.1.bak")).unwrap();
+
+        assert!(TaskFile::rename_list(&dir, "release", "tasks").is_err());
+        assert!(TaskFile::rename_list(&dir, "tasks", "chores").is_err());
+        assert!(TaskFile::rename_list(&dir, "release", "errands").is_ok());
+        assert_eq!(TaskFile::get_lists(&dir), ["errands", "tasks"]);
+        assert!(dir.join("errands.json.1.bak").exists());
+        assert!(!dir.join("release.json.1.bak").exists());
+        // Lock files stay where they are, another process may be waiting on them.
+        assert!(dir.join("release.json.lock").exists());
+
+        assert!(TaskFile::delete_list(&dir, "tasks").is_err());
+        assert!(TaskFile::delete_list(&dir, "release").is_err());
+        assert!(TaskFile::delete_list(&dir, "errands").is_ok());
+        assert_eq!(TaskFile::get_lists(&dir), ["tasks"]);
+        assert!(!dir.join("errands.json.1.bak").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // PROJECT FILES
+    #[test]
+    fn test_find_project() {
+        let dir = get_test_dir("find_project");
+        let nested = dir.join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(TaskFile::find_project(&nested), None);
+
+        let file = TaskFile::init(&dir).unwrap();
+        assert_eq!(file, dir.join(PROJECT_FILE));
+        assert_eq!(TaskFile::find_project(&nested), Some(file));
+        assert!(TaskFile::init(&dir).is_err());
+
+        // A `.tsk` directory without a default list isn't a project.
+        let proj_dir = dir.join("a").join(PROJECT_DIR);
+        fs::create_dir(&proj_dir).unwrap();
+        assert_eq!(
+            TaskFile::find_project(&nested),
+            Some(dir.join(PROJECT_FILE))
+        );
+
+        // The closest project wins.
+        TaskFile::create_empty(&TaskFile::get_list_path(&proj_dir, DEFAULT_LIST)).unwrap();
+        assert_eq!(
+            TaskFile::find_project(&nested),
+            Some(dir.join("a").join(PROJECT_DIR))
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_new_list_has_no_tasks() {
+        let dir = get_test_dir("new_list");
+        TaskFile::create_list(&dir, "work").unwrap();
+
+        let file = TaskFile::get_list_path(&dir, "work");
+        let tf = TaskFile::load(None, Some(&file)).unwrap();
+        assert!(tf.tasks.is_empty());
+        assert!(tf.get_notices().is_empty());
+
+        drop(tf);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_init_creates_empty_task_file() {
+        let dir = get_test_dir("init");
+        let file = TaskFile::init(&dir).unwrap();
+
+        let json = fs::read_to_string(file).unwrap();
+        let tf: TaskFile = serde_json::from_str(&json).unwrap();
+        assert!(tf.tasks.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_init_extends_gitignore() {
+        let dir = get_test_dir("init_gitignore");
+        fs::write(dir.join(".gitignore"), "target\n.tsk/*.json.*").unwrap();
+        TaskFile::init(&dir).unwrap();
+
+        let gitignore = fs::read_to_string(dir.join(".gitignore")).unwrap();
+        assert_eq!(
+            gitignore,
+            "target\n.tsk/*.json.*\n# tsk lock, journal and backups\n.tsk.json.*\n"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // SAVING
+    #[test]
+    fn test_save_replaces_file() {
+        let dir = get_test_dir("save");
+        let mut tf = get_test_task_file();
+        tf.task_file_path = dir.join("tasks.json");
+        fs::write(&tf.task_file_path, "old contents").unwrap();
+        tf.loaded_hash = Some(TaskFile::hash("old contents"));
+
+        tf.save().unwrap();
+
+        let json = fs::read_to_string(&tf.task_file_path).unwrap();
+        let saved: TaskFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(saved.tasks.len(), 2);
+
+        // Only the task file and the backup of its old contents are left behind.
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 2);
+        let backup = TaskFile::get_backup_path(&tf.task_file_path, 1);
+        assert_eq!(fs::read_to_string(backup).unwrap(), "old contents");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_save_removes_tmp_file() {
+        let dir = get_test_dir("failed_save");
+        let target = dir.join("tasks.json");
+
+        // Renaming a file over a non empty directory fails.
+        fs::create_dir_all(target.join("inner")).unwrap();
+        assert!(TaskFile::write_atomic(&target, "new contents").is_err());
+
+        assert!(target.join("inner").is_dir());
+        assert!(!dir.join("tasks.json.tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_through_symlink() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = get_test_dir("save_symlink");
+        let target = dir.join("dotfiles").join("tasks.json");
+        let link = dir.join("tasks.json");
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::write(&target, "old contents").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        TaskFile::write_atomic(&link, "new contents").unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new contents");
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!dir.join("dotfiles").join("tasks.json.tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // LOCKING
+    #[test]
+    fn test_lock_is_exclusive() {
+        let dir = get_test_dir("lock");
+        let file = dir.join("tasks.json");
+        let timeout = Duration::from_millis(100);
+
+        let lock = TaskFile::lock(&file, timeout);
+        assert!(lock.is_ok());
+        assert!(TaskFile::lock(&file, timeout).is_err());
+
+        drop(lock);
+        assert!(TaskFile::lock(&file, timeout).is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lock_is_shared_through_symlinks() {
+        let dir = get_test_dir("lock_symlink");
+        let file = dir.join("tasks.json");
+        let link = dir.join("link.json");
+        fs::write(&file, "{}").unwrap();
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        let timeout = Duration::from_millis(100);
+
+        let _lock = TaskFile::lock(&file, timeout).unwrap();
+        assert!(TaskFile::lock(&link, timeout).is_err());
+        assert!(TaskFile::lock(&dir.join(".").join("tasks.json"), timeout).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
     #[test]
-    #[ignore = "Manually view the output of this test"]
-    fn test_print() {
-        let tf = get_test_task_file();
-        tf.print(true);
+    fn test_detect_modified_task_file() {
+        let dir = get_test_dir("modified");
+        let file = dir.join("tasks.json");
+        let mut tf = get_test_task_file();
+        tf.task_file_path = file.clone();
+        tf.save().unwrap();
+
+        let tf = TaskFile::load(None, Some(&file)).unwrap();
+        assert!(tf.check_unmodified().is_ok());
+
+        fs::write(&file, "{}").unwrap();
+        assert!(tf.check_unmodified().is_err());
+        fs::remove_dir_all(dir).unwrap();
     }
 
-    // ADDING TASKS
+    // BACKUPS
     #[test]
-    fn test_add_task_top() {
+    fn test_backups_are_rotated() {
+        let dir = get_test_dir("backups");
+        let file = dir.join("tasks.json");
         let mut tf = get_test_task_file();
-        tf.add_task("three", "top", "");
+        tf.task_file_path = file.clone();
+        tf.save().unwrap();
+        assert!(!TaskFile::get_backup_path(&file, 1).exists());
 
-        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
-        assert_eq!(v, ["three", "one", "two"]);
+        for i in 0..MAX_BACKUPS + 2 {
+            tf.add_task(&i.to_string(), Position::Top, None).unwrap();
+            tf.save().unwrap();
+        }
+
+        // The newest backup is the version before the last save.
+        let json = fs::read_to_string(TaskFile::get_backup_path(&file, 1)).unwrap();
+        let backup = TaskFile::from_json(&json).unwrap();
+        assert_eq!(backup.tasks.len(), tf.tasks.len() - 1);
+
+        assert!(TaskFile::get_backup_path(&file, MAX_BACKUPS).exists());
+        assert!(!TaskFile::get_backup_path(&file, MAX_BACKUPS + 1).exists());
+
+        // Saving without changes doesn't push out older backups.
+        tf.save().unwrap();
+        let again = fs::read_to_string(TaskFile::get_backup_path(&file, 1)).unwrap();
+        assert_eq!(json, again);
+
+        fs::remove_dir_all(dir).unwrap();
     }
 
     #[test]
-    fn test_add_task_bottom() {
+    fn test_restore_backup() {
+        let dir = get_test_dir("restore");
+        let file = dir.join("tasks.json");
         let mut tf = get_test_task_file();
-        tf.add_task("three", "bot", "");
-        tf.add_task("four", "bottom", "");
+        tf.task_file_path = file.clone();
+        tf.save().unwrap();
+        tf.delete_tasks(&get_ids("1")).unwrap();
+        tf.save().unwrap();
 
-        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
-        assert_eq!(v, ["one", "two", "three", "four"]);
+        fs::write(&file, "{ corrupted").unwrap();
+        assert!(TaskFile::restore_backup(&file, 3).is_err());
+        assert!(TaskFile::restore_backup(&file, 1).is_ok());
+
+        let tf = TaskFile::load(None, Some(&file)).unwrap();
+        assert_eq!(tf.tasks.len(), 2);
+        drop(tf);
+
+        // The replaced contents are kept.
+        let backup = TaskFile::get_backup_path(&file, 1);
+        assert_eq!(fs::read_to_string(backup).unwrap(), "{ corrupted");
+        assert!(TaskFile::restore_backup(&file, 1).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // DUE DATES
+    fn get_date(d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2026, 11, d)
     }
 
     #[test]
-    fn test_add_sub_top() {
+    fn test_set_due() {
         let mut tf = get_test_task_file();
-        tf.add_task("sub", "top", "2");
+        tf.set_due(&get_ids("1,2.1"), get_date(3)).unwrap();
+        assert_eq!(tf.tasks[0].due, get_date(3));
+        assert_eq!(tf.tasks[1].subtasks[0].due, get_date(3));
+        assert_eq!(tf.tasks[1].due, None);
+
+        tf.set_due(&get_ids("1"), None).unwrap();
+        assert_eq!(tf.tasks[0].due, None);
+
+        tf.add_task("three", Position::Bottom, Some(&get_id("2")))
+            .unwrap()
+            .set_due(get_date(4));
+        assert_eq!(tf.tasks[1].subtasks[2].due, get_date(4));
+    }
 
-        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
-        let sv0: Vec<&str> = tf.tasks[0]
-            .subtasks
-            .iter()
-            .map(|t| t.contents.as_str())
-            .collect();
-        let sv1: Vec<&str> = tf.tasks[1]
-            .subtasks
-            .iter()
-            .map(|t| t.contents.as_str())
-            .collect();
+    #[test]
+    fn test_due_is_saved() {
+        let mut tf = get_test_task_file();
+        tf.set_due(&get_ids("2"), get_date(3)).unwrap();
 
-        assert_eq!(v, ["one", "two"]);
-        assert_eq!(sv0, ["one", "two"]);
-        assert_eq!(sv1, ["sub", "one", "two"]);
+        let json = serde_json::to_string(&tf).unwrap();
+        assert!(json.contains(r#""due":"2026-11-03""#));
+        assert_eq!(json.matches("due").count(), 1);
+
+        let tf = TaskFile::from_json(&json).unwrap();
+        assert_eq!(tf.tasks[1].due, get_date(3));
     }
 
     #[test]
-    fn test_add_sub_bottom() {
+    fn test_print_filter_and_sort_by_due() {
+        let mut tf = get_test_task_file();
+        tf.set_due(&get_ids("1.2"), get_date(5)).unwrap();
+        tf.set_due(&get_ids("2"), get_date(3)).unwrap();
+        tf.set_due(&get_ids("2.1"), get_date(1)).unwrap();
+
+        let ids = |opts: &PrintOptions| -> Vec<String> {
+            TaskFile::get_shown("", &tf.tasks, opts)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect()
+        };
+
+        // Parents of tasks that pass the filter are shown, ids stay the same.
+        let opts = PrintOptions {
+            due_before: get_date(4),
+            ..Default::default()
+        };
+        assert_eq!(ids(&opts), ["2"]);
+        let subtasks = TaskFile::get_shown("2.", &tf.tasks[1].subtasks, &opts);
+        assert_eq!(subtasks.len(), 1);
+        assert_eq!(subtasks[0].0, "2.1");
+
+        let opts = PrintOptions {
+            due_before: get_date(5),
+            ..Default::default()
+        };
+        assert_eq!(ids(&opts), ["1", "2"]);
+
+        let opts = PrintOptions {
+            sort_by: Some(SortKey::Due),
+            ..Default::default()
+        };
+        assert_eq!(ids(&opts), ["2", "1"]);
+    }
+
+    // PRIORITIES
+    #[test]
+    fn test_set_priority() {
         let mut tf = get_test_task_file();
-        tf.add_task("sub3", "bot", "2");
-        tf.add_task("sub4", "bottom", "2");
+        tf.set_priority(&get_ids("2,1.1"), Some(Priority::High))
+            .unwrap();
+        assert_eq!(tf.tasks[1].priority, Some(Priority::High));
+        assert_eq!(tf.tasks[0].subtasks[0].priority, Some(Priority::High));
+
+        tf.set_priority(&get_ids("2"), None).unwrap();
+        assert_eq!(tf.tasks[1].priority, None);
+
+        let json = serde_json::to_string(&tf).unwrap();
+        assert!(json.contains(r#""priority":"high""#));
+
+        assert_eq!("M".parse(), Ok(Priority::Medium));
+        assert!(matches!(
+            "urgent".parse::<Priority>(),
+            Err(Error::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_sort_by_priority() {
+        let mut tf = get_test_task_file();
+        tf.set_priority(&get_ids("2"), Some(Priority::Low)).unwrap();
+        tf.set_priority(&get_ids("1.1"), Some(Priority::Low))
+            .unwrap();
+        tf.set_priority(&get_ids("1.2"), Some(Priority::High))
+            .unwrap();
+        tf.add_task("three", Position::Bottom, None).unwrap();
+        tf.add_task("four", Position::Bottom, None)
+            .unwrap()
+            .set_priority(Some(Priority::Medium));
+
+        tf.sort_tasks(SortKey::Priority);
 
         let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
-        let sv0: Vec<&str> = tf.tasks[0]
-            .subtasks
-            .iter()
-            .map(|t| t.contents.as_str())
-            .collect();
-        let sv1: Vec<&str> = tf.tasks[1]
+        assert_eq!(v, ["four", "two", "one", "three"]);
+
+        let v: Vec<&str> = tf.tasks[2]
             .subtasks
             .iter()
             .map(|t| t.contents.as_str())
             .collect();
+        assert_eq!(v, ["two", "one"]);
+    }
 
-        assert_eq!(v, ["one", "two"]);
-        assert_eq!(sv0, ["one", "two"]);
-        assert_eq!(sv1, ["one", "two", "sub3", "sub4"]);
+    #[test]
+    fn test_print_filter_by_priority() {
+        let mut tf = get_test_task_file();
+        tf.set_priority(&get_ids("1.2"), Some(Priority::High))
+            .unwrap();
+        tf.set_priority(&get_ids("2"), Some(Priority::Low)).unwrap();
+
+        let opts = PrintOptions {
+            min_priority: Some(Priority::Medium),
+            ..Default::default()
+        };
+        let shown = TaskFile::get_shown("", &tf.tasks, &opts);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].0, "1");
     }
 
+    // TAGS
     #[test]
-    fn test_adding_sub_undoes_task() {
-        let mut tf = get_done_test_task_file();
+    fn test_add_task_takes_tags() {
+        let mut tf = get_test_task_file();
+        let task = tf
+            .add_task("buy milk +groceries +3d +groceries", Position::Top, None)
+            .unwrap();
 
-        assert_eq!(tf.tasks[1].done, true);
-        tf.add_task("sub", "top", "2");
-        assert_eq!(tf.tasks[1].done, false);
+        assert_eq!(task.contents, "buy milk +3d");
+        assert_eq!(task.tags, ["groceries"]);
     }
 
-    // MARKING TASKS
     #[test]
-    fn test_mark_tasks() {
+    fn test_tag_and_untag_tasks() {
         let mut tf = get_test_task_file();
-        tf.mark_tasks("1", true);
-        tf.mark_tasks("2", true);
+        let tags = ["backend".to_owned(), "urgent".to_owned()];
 
-        assert!(tf.tasks[0].done);
-        assert!(tf.tasks[1].done);
+        tf.tag_tasks(&get_ids("1,2.2"), &tags, true).unwrap();
+        tf.tag_tasks(&get_ids("1"), &tags[..1], true).unwrap();
+        assert_eq!(tf.tasks[0].tags, tags);
+        assert_eq!(tf.tasks[1].subtasks[1].tags, tags);
 
-        tf.mark_tasks("1", false);
-        tf.mark_tasks("2", false);
+        tf.tag_tasks(&get_ids("1"), &tags[1..], false).unwrap();
+        assert_eq!(tf.tasks[0].tags, ["backend"]);
+    }
 
-        assert!(!tf.tasks[0].done);
-        assert!(!tf.tasks[1].done);
+    #[test]
+    fn test_resolve_tags() {
+        let mut tf = get_test_task_file();
+        tf.tag_tasks(&get_ids("1.2,2"), &["backend".to_owned()], true)
+            .unwrap();
+        tf.tag_tasks(&get_ids("2.1"), &["urgent".to_owned()], true)
+            .unwrap();
+
+        let select = |ids: &str| {
+            let ids: Vec<String> = tf
+                .select(&get_ids(ids))
+                .unwrap()
+                .iter()
+                .map(|i| i.to_string())
+                .collect();
+            ids.join(",")
+        };
+        assert_eq!(select("1,2"), "1,2");
+        assert_eq!(select("+backend"), "1.2,2");
+        assert_eq!(select("+urgent,1"), "1,2.1");
+
+        assert!(matches!(
+            tf.select(&get_ids("+frontend")),
+            Err(Error::OutOfBounds(_))
+        ));
+        assert!(matches!(
+            tf.resolve(&"+backend".parse().unwrap()),
+            Err(Error::InvalidId(_))
+        ));
+        assert!("1..+urgent".parse::<Selection>().is_err());
     }
 
     #[test]
-    fn test_mark_sub() {
+    fn test_print_filter_by_tags() {
         let mut tf = get_test_task_file();
-        tf.mark_tasks("1.1", true);
-        assert!(!tf.tasks[0].done);
-        assert!(tf.tasks[0].subtasks[0].done);
+        tf.tag_tasks(&get_ids("1.2,2"), &["backend".to_owned()], true)
+            .unwrap();
+        tf.tag_tasks(&get_ids("2"), &["urgent".to_owned()], true)
+            .unwrap();
+
+        let opts = PrintOptions {
+            tags: vec!["backend".to_owned(), "urgent".to_owned()],
+            ..Default::default()
+        };
+        let shown = TaskFile::get_shown("", &tf.tasks, &opts);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].0, "2");
+    }
 
-        tf.mark_tasks("1.2", true);
-        assert!(tf.tasks[0].done);
-        assert!(tf.tasks[0].subtasks[1].done);
-        assert!(!tf.tasks[1].done);
+    // PRINT FILTERS
+    #[test]
+    fn test_print_filter_by_status() {
+        let mut tf = get_test_task_file();
+        tf.mark_tasks(&get_ids("1.1"), true).unwrap();
+        let ids = |opts: &PrintOptions| -> Vec<String> {
+            let shown = TaskFile::get_shown("", &tf.tasks[0].subtasks, opts);
+            shown.into_iter().map(|(id, _)| id).collect()
+        };
 
-        tf.mark_tasks("1.1", false);
-        assert!(!tf.tasks[0].done);
-        assert!(!tf.tasks[0].subtasks[0].done);
+        let opts = PrintOptions {
+            done: true,
+            ..Default::default()
+        };
+        assert_eq!(ids(&opts), ["1"]);
 
-        tf.mark_tasks("1.2", false);
-        assert!(!tf.tasks[0].subtasks[1].done);
+        let opts = PrintOptions {
+            pending: true,
+            ..Default::default()
+        };
+        assert_eq!(ids(&opts), ["2"]);
     }
 
     #[test]
-    fn test_marking_task_done_marks_subtasks() {
+    fn test_print_filter_by_match() {
         let mut tf = get_test_task_file();
+        tf.edit_tasks(&get_ids("2.2"), "release notes").unwrap();
 
-        tf.mark_tasks("2", true);
-        assert!(!tf.tasks[0].done);
-        assert!(tf.tasks[1].done);
-        assert!(tf.tasks[1].subtasks[0].done);
-        assert!(tf.tasks[1].subtasks[1].done);
+        let opts = PrintOptions {
+            pattern: Some(Regex::new("release").unwrap()),
+            ..Default::default()
+        };
+        let shown = TaskFile::get_shown("", &tf.tasks, &opts);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].0, "2");
 
-        tf.mark_tasks("2", false);
-        assert!(!tf.tasks[1].done);
-        assert!(tf.tasks[1].subtasks[0].done);
-        assert!(tf.tasks[1].subtasks[1].done);
+        let opts = PrintOptions {
+            top_level_only: true,
+            ..opts
+        };
+        assert!(TaskFile::get_shown("", &tf.tasks, &opts).is_empty());
     }
 
-    // MOVING TASKS
     #[test]
-    fn test_move_task() {
+    fn test_print_filters_combined() {
         let mut tf = get_test_task_file();
-        tf.add_task("other", "bottom", "");
-        tf.add_task("more", "bottom", "");
-        tf.move_task("3", "1");
+        tf.tag_tasks(&get_ids("1"), &["backend".to_owned()], true)
+            .unwrap();
+        tf.set_priority(&get_ids("2"), Some(Priority::High))
+            .unwrap();
+
+        let opts = PrintOptions {
+            tags: vec!["backend".to_owned()],
+            min_priority: Some(Priority::High),
+            top_level_only: true,
+            ..Default::default()
+        };
+        assert!(TaskFile::get_shown("", &tf.tasks, &opts).is_empty());
 
-        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
-        assert_eq!(v, ["other", "one", "two", "more"]);
+        let opts = PrintOptions { any: true, ..opts };
+        assert_eq!(TaskFile::get_shown("", &tf.tasks, &opts).len(), 2);
 
-        tf.move_task("1", "3");
+        // Without filters `any` still prints everything.
+        let opts = PrintOptions {
+            any: true,
+            ..Default::default()
+        };
+        assert_eq!(TaskFile::get_shown("", &tf.tasks, &opts).len(), 2);
+    }
 
-        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
-        assert_eq!(v, ["one", "two", "other", "more"]);
+    // NOTES
+    #[test]
+    fn test_set_notes() {
+        let mut tf = get_test_task_file();
+        tf.set_notes(&get_id("2.1"), "first line\nsecond line\n\n")
+            .unwrap();
+        assert_eq!(tf.tasks[1].subtasks[0].notes, "first line\nsecond line");
+        assert_eq!(
+            tf.get_task_notes(&get_id("2.1")).unwrap(),
+            "first line\nsecond line"
+        );
+
+        let json = serde_json::to_string(&tf).unwrap();
+        assert_eq!(json.matches("notes").count(), 1);
+
+        tf.set_notes(&get_id("2.1"), "\n").unwrap();
+        assert!(tf.tasks[1].subtasks[0].notes.is_empty());
     }
 
+    // TIMESTAMPS
     #[test]
-    fn test_move_subtask() {
+    fn test_timestamps() {
         let mut tf = get_test_task_file();
-        tf.add_task("other", "top", "1");
-        tf.move_task("1.1", "1.3");
+        let task = tf
+            .add_task("three", Position::Top, Some(&get_id("2")))
+            .unwrap();
+        assert!(task.created.is_some());
+        assert_eq!(task.created, task.modified);
+        assert!(task.completed.is_none());
+
+        tf.edit_tasks(&get_ids("1"), "edited").unwrap();
+        assert!(tf.tasks[0].modified.is_some());
+        assert!(tf.tasks[0].created.is_none());
+
+        tf.mark_tasks(&get_ids("2.1"), true).unwrap();
+        let completed = tf.tasks[1].subtasks[0].completed;
+        assert!(completed.is_some());
+        assert!(tf.tasks[1].completed.is_none());
+
+        // Marking a done task again keeps when it was completed.
+        tf.mark_tasks(&get_ids("2"), true).unwrap();
+        assert_eq!(tf.tasks[1].subtasks[0].completed, completed);
+        assert!(tf.tasks[1].subtasks[1].completed.is_some());
+        assert!(tf.tasks[1].completed.is_some());
+
+        tf.append_to_tasks(&get_ids("2.2"), "more").unwrap();
+        assert!(tf.tasks[1].subtasks[1].completed.is_none());
+        assert!(tf.tasks[1].completed.is_none());
+    }
 
-        let sv0: Vec<&str> = tf.tasks[0]
-            .subtasks
-            .iter()
-            .map(|t| t.contents.as_str())
-            .collect();
-        assert_eq!(sv0, ["one", "two", "other"]);
+    #[test]
+    fn test_print_filter_by_timestamps() {
+        let mut tf = get_test_task_file();
+        tf.mark_tasks(&get_ids("1.2"), true).unwrap();
+        tf.tasks[1].completed = Some(Utc::now() - chrono::Days::new(10));
 
-        tf.move_task("1.3", "1.1");
-        let sv0: Vec<&str> = tf.tasks[0]
-            .subtasks
-            .iter()
-            .map(|t| t.contents.as_str())
-            .collect();
-        assert_eq!(sv0, ["other", "one", "two"]);
+        let opts = PrintOptions {
+            done_since: Some(date::today()),
+            ..Default::default()
+        };
+        let shown = TaskFile::get_shown("", &tf.tasks, &opts);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].0, "1");
+
+        let opts = PrintOptions {
+            created_since: Some(date::today()),
+            ..Default::default()
+        };
+        assert!(TaskFile::get_shown("", &tf.tasks, &opts).is_empty());
     }
 
+    // RECURRING TASKS
     #[test]
-    fn test_move_task_to_subtask_vice_versa() {
+    fn test_recurring_task_comes_back() {
         let mut tf = get_test_task_file();
-        tf.add_task("other", "top", "1");
-        tf.move_task("1.1", "3");
+        let week: Recurrence = "every week".parse().unwrap();
+        tf.set_recurrence(&get_ids("1"), Some(week)).unwrap();
+        tf.mark_tasks(&get_ids("1.1"), true).unwrap();
+
+        tf.mark_tasks(&get_ids("1"), true).unwrap();
+        let t = &tf.tasks[0];
+        assert!(!t.done);
+        assert!(t.completed.is_none());
+        assert!(t.subtasks.iter().all(|st| !st.done));
+        assert_eq!(t.completions.len(), 1);
+        assert_eq!(t.due, Some(week.next(None, date::today())));
+
+        // Completing the subtasks completes the task.
+        tf.mark_tasks(&get_ids("1.1,1.2"), true).unwrap();
+        assert_eq!(tf.tasks[0].completions.len(), 2);
+        assert!(!tf.tasks[0].done);
+    }
 
-        assert_eq!(tf.tasks[0].contents, "one");
-        assert_eq!(tf.tasks[0].subtasks[0].contents, "one");
-        assert_eq!(tf.tasks[0].subtasks[1].contents, "two");
-        assert_eq!(tf.tasks[1].contents, "two");
-        assert_eq!(tf.tasks[2].contents, "other");
+    #[test]
+    fn test_recurring_task_recurs_once() {
+        let mut tf = get_test_task_file();
+        let week: Recurrence = "every week".parse().unwrap();
+        tf.set_recurrence(&get_ids("1"), Some(week)).unwrap();
+        tf.mark_tasks(&get_ids("1.2"), true).unwrap();
+
+        // Completing 1.1 completes 1 as well, which must only recur once.
+        tf.mark_tasks(&get_ids("1,1.1"), true).unwrap();
+        let t = &tf.tasks[0];
+        assert_eq!(t.completions.len(), 1);
+        assert_eq!(t.due, Some(week.next(None, date::today())));
+        assert!(!t.done);
+        assert!(t.subtasks.iter().all(|st| !st.done));
+
+        tf.mark_tasks(&get_ids("pending"), true).unwrap();
+        assert_eq!(tf.tasks[0].completions.len(), 2);
+    }
 
-        tf.move_task("1", "3.1");
-        assert_eq!(tf.tasks[0].contents, "two");
-        assert_eq!(tf.tasks[1].contents, "other");
-        assert_eq!(tf.tasks[1].subtasks[0].contents, "one");
+    #[test]
+    fn test_recurring_subtask_does_not_complete_parent() {
+        let mut tf = get_test_task_file();
+        tf.set_recurrence(&get_ids("2.2"), Some("every day".parse().unwrap()))
+            .unwrap();
+        tf.mark_tasks(&get_ids("2.1,2.2"), true).unwrap();
 
-        tf.move_task("2", "1.1");
-        assert_eq!(tf.tasks[0].contents, "two");
-        assert_eq!(tf.tasks[0].subtasks[0].contents, "other");
-        assert_eq!(tf.tasks[0].subtasks[1].contents, "one");
-        assert_eq!(tf.tasks[0].subtasks[2].contents, "two");
+        assert!(tf.tasks[1].subtasks[0].done);
+        assert!(!tf.tasks[1].subtasks[1].done);
+        assert!(!tf.tasks[1].done);
     }
 
-    // SWAPPING TASKS
     #[test]
-    fn test_swap_tasks() {
+    fn test_recurring_subtask_recurs_with_parent() {
         let mut tf = get_test_task_file();
-        tf.add_task("other", "top", "");
-        tf.swap_tasks("1", "3");
+        let day: Recurrence = "every day".parse().unwrap();
+        tf.set_recurrence(&get_ids("2.2"), Some(day)).unwrap();
+        tf.mark_tasks(&get_ids("2"), true).unwrap();
 
-        let v: Vec<&str> = tf.tasks.iter().map(|t| t.contents.as_str()).collect();
-        assert_eq!(v, ["two", "one", "other"]);
+        let st = &tf.tasks[1].subtasks[1];
+        assert!(!st.done);
+        assert_eq!(st.completions.len(), 1);
+        assert_eq!(st.due, Some(day.next(None, date::today())));
+
+        tf.clear_dones();
+        assert_eq!(tf.tasks.len(), 2);
+        assert_eq!(tf.tasks[1].subtasks.len(), 1);
+        assert!(tf.tasks[1].subtasks[0].recurrence.is_some());
     }
 
     #[test]
-    fn test_swap_subtasks() {
-        let mut tf = get_test_task_file();
-        tf.add_task("other", "top", "1");
-        tf.swap_tasks("1.1", "1.3");
+    fn test_clear_keeps_recurring_tasks() {
+        let mut tf = get_done_test_task_file();
+        tf.tasks[1].recurrence = Some("every month".parse().unwrap());
+        tf.clear_dones();
 
-        let sv0: Vec<&str> = tf.tasks[0]
-            .subtasks
-            .iter()
-            .map(|t| t.contents.as_str())
-            .collect();
-        assert_eq!(sv0, ["two", "one", "other"]);
+        assert_eq!(tf.tasks.len(), 1);
+        assert!(tf.tasks[0].recurrence.is_some());
+        assert!(tf.tasks[0].subtasks.is_empty());
     }
 
+    // TIME TRACKING
     #[test]
-    fn test_swap_task_subtask() {
+    fn test_only_one_timer_runs() {
         let mut tf = get_test_task_file();
-        tf.add_task("other", "top", "");
-        tf.swap_tasks("1", "2.2");
+        tf.start_timer(&get_id("1.2")).unwrap();
+        assert!(tf.start_timer(&get_id("1.2")).is_err());
+        assert_eq!(tf.get_running().unwrap().0, get_id("1.2"));
+
+        tf.start_timer(&get_id("2")).unwrap();
+        assert_eq!(tf.get_running().unwrap().0, get_id("2"));
+        assert!(tf.tasks[0].subtasks[1].intervals[0].end.is_some());
+
+        assert_eq!(tf.stop_timer().unwrap(), get_id("2"));
+        assert!(tf.get_running().is_none());
+        assert!(tf.stop_timer().is_err());
+        assert_eq!(tf.tasks[1].intervals.len(), 1);
+    }
 
-        assert_eq!(tf.tasks[0].contents, "two");
-        assert_eq!(tf.tasks[1].subtasks[1].contents, "other");
+    #[test]
+    fn test_tracked_time() {
+        let mut tf = get_test_task_file();
+        let now = Utc::now();
+        let hours_ago = |h| now - chrono::Duration::hours(h);
+        tf.tasks[0].intervals = vec![
+            Interval {
+                start: hours_ago(5),
+                end: Some(hours_ago(4)),
+            },
+            Interval {
+                start: hours_ago(2),
+                end: None,
+            },
+        ];
+        assert!(tf.tasks[0].is_running());
+        assert_eq!(tf.tasks[0].get_tracked_secs() / 60, 180);
+
+        // Completing the task stops its timer.
+        tf.mark_tasks(&get_ids("1"), true).unwrap();
+        assert!(!tf.tasks[0].is_running());
+        assert!(tf.get_running().is_none());
+    }
 
-        tf.swap_tasks("2.2", "1");
-        assert_eq!(tf.tasks[0].contents, "other");
-        assert_eq!(tf.tasks[1].subtasks[1].contents, "two");
+    // SCHEMA MIGRATIONS
+    #[test]
+    fn test_migrate_unversioned_file() {
+        let json = r#"{
+            "task_file_path": "/some/other/machine/tasks.json",
+            "tasks": [{ "contents": "one", "done": false, "subtasks": [] }]
+        }"#;
+        let tf = TaskFile::from_json(json).unwrap();
+        assert_eq!(tf.version, SCHEMA_VERSION);
+        assert_eq!(tf.tasks[0].uid.len(), 16);
+        assert_eq!(tf.task_file_path, PathBuf::new());
+        assert_eq!(tf.tasks[0].contents, "one");
+
+        let json = serde_json::to_string(&tf).unwrap();
+        assert!(!json.contains("task_file_path"));
+        assert!(json.contains(&format!("\"version\":{SCHEMA_VERSION}")));
     }
 
-    // APPENDING TASK
     #[test]
-    fn test_append_task() {
-        let mut tf = get_test_task_file();
-        tf.append_to_task("1", "new");
-        tf.append_to_task("1.1", "new");
+    fn test_migrate_subtasks_to_tasks() {
+        let json = r#"{
+            "version": 2,
+            "tasks": [{
+                "uid": "a", "contents": "one", "done": false,
+                "subtasks": [{ "uid": "b", "contents": "sub", "done": true }]
+            }]
+        }"#;
+        let tf = TaskFile::from_json(json).unwrap();
+        assert_eq!(tf.tasks[0].subtasks[0].contents, "sub");
+        assert!(tf.tasks[0].subtasks[0].subtasks.is_empty());
+    }
 
-        assert_eq!(tf.tasks[0].contents, "one new");
-        assert_eq!(tf.tasks[0].subtasks[0].contents, "one new");
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let json = format!(r#"{{ "version": {}, "tasks": [] }}"#, SCHEMA_VERSION + 1);
+        assert!(matches!(TaskFile::from_json(&json), Err(Error::Command(_))));
+        assert!(matches!(
+            TaskFile::from_json("[]"),
+            Err(Error::Corrupt { .. })
+        ));
     }
 
     #[test]
-    fn test_append_task_undo_done() {
-        let mut tf = get_done_test_task_file();
-        tf.append_to_task("1", "new");
-        assert!(!tf.tasks[0].done);
+    fn test_invalid_json_is_corrupt() {
+        let res = TaskFile::from_json("{\n  \"tasks\": [,]\n}");
+        assert!(matches!(
+            res,
+            Err(Error::Corrupt {
+                line: 2,
+                column: 13,
+                ..
+            })
+        ));
+    }
 
-        tf = get_done_test_task_file();
-        tf.append_to_task("1.1", "new");
+    // UNIQUE IDS
+    #[test]
+    fn test_new_uids_are_unique() {
+        let mut tf = get_test_task_file();
+        tf.add_task("three", Position::Bottom, None).unwrap();
+        tf.add_task("four", Position::Bottom, None).unwrap();
+        tf.add_task("sub", Position::Bottom, Some(&get_id("3")))
+            .unwrap();
 
-        assert!(!tf.tasks[0].done);
-        assert!(!tf.tasks[0].subtasks[0].done);
+        assert_ne!(tf.tasks[2].uid, tf.tasks[3].uid);
+        assert_ne!(tf.tasks[2].uid, tf.tasks[2].subtasks[0].uid);
     }
 
-    // EDITING TASK
     #[test]
-    fn test_edit_task() {
+    fn test_uids_follow_moved_tasks() {
         let mut tf = get_test_task_file();
-        tf.edit_task("1", "new");
-        tf.edit_task("1.1", "newer");
+        let uid = tf.tasks[0].subtasks[1].uid.clone();
 
-        assert_eq!(tf.tasks[0].contents, "new");
-        assert_eq!(tf.tasks[0].subtasks[0].contents, "newer");
+        tf.move_tasks(&get_ids("1.2"), &get_id("1")).unwrap();
+        assert_eq!(tf.tasks[0].uid, uid);
+
+        tf.swap_tasks(&get_id("1"), &get_id("2.1")).unwrap();
+        assert_eq!(tf.tasks[1].subtasks[0].uid, uid);
     }
 
     #[test]
-    fn test_edit_task_undo_done() {
-        let mut tf = get_done_test_task_file();
-        tf.edit_task("1", "new");
-        assert!(!tf.tasks[0].done);
+    fn test_resolve_uids() {
+        let tf = get_test_task_file();
+        let uid = |id: &str| {
+            format!(
+                "@{}",
+                tf.get_all()
+                    .into_iter()
+                    .find(|u| u.0 == get_id(id))
+                    .unwrap()
+                    .1
+                    .uid
+            )
+        };
 
-        tf = get_done_test_task_file();
-        tf.edit_task("1.1", "new");
+        let resolve = |ids: &str| tf.resolve(&ids.parse().unwrap());
+        assert_eq!(resolve("2.1").unwrap(), get_id("2.1"));
+        assert_eq!(resolve(&uid("2")).unwrap(), get_id("2"));
+        assert_eq!(resolve(&uid("1.2")).unwrap(), get_id("1.2"));
+
+        let ids = format!("{},1.1", uid("2.2"));
+        assert_eq!(
+            tf.select(&get_ids(&ids)).unwrap(),
+            [get_id("1.1"), get_id("2.2")]
+        );
+        let ids = format!("{}..{}", uid("2.2"), uid("1"));
+        assert_eq!(
+            tf.select(&get_ids(&ids)).unwrap(),
+            [get_id("1"), get_id("2.2")]
+        );
+
+        // A prefix is enough as long as it's unique.
+        assert!(matches!(resolve("@a"), Err(Error::InvalidId(_))));
+        assert!(matches!(resolve("@ffff"), Err(Error::OutOfBounds(_))));
+        assert!("@".parse::<Target>().is_err());
+    }
 
-        assert!(!tf.tasks[0].done);
-        assert!(!tf.tasks[0].subtasks[0].done);
+    #[test]
+    fn test_select_paths() {
+        let tf = get_test_task_file();
+        let select = |ids: &str| {
+            tf.select(&get_ids(ids)).map(|ids| {
+                let ids: Vec<String> = ids.iter().map(|i| i.to_string()).collect();
+                ids.join(",")
+            })
+        };
+
+        assert_eq!(select("-1").unwrap(), "2");
+        assert_eq!(select("last,1.last").unwrap(), "1.2,2");
+        assert_eq!(select("-2.-1").unwrap(), "1.2");
+        assert_eq!(select("2.*").unwrap(), "2.1,2.2");
+        assert_eq!(select("*.1").unwrap(), "1.1,2.1");
+        assert_eq!(select("1.2..-1").unwrap(), "1.2,2");
+        assert_eq!(select("1,2.1..2.-1").unwrap(), "1,2.1,2.2");
+
+        assert!(matches!(select("-3"), Err(Error::OutOfBounds(_))));
+        assert!(matches!(select("3.-1"), Err(Error::OutOfBounds(_))));
+        assert!(matches!(select("1.1.*"), Err(Error::OutOfBounds(_))));
+        assert!(matches!(
+            tf.resolve(&"2.*".parse().unwrap()),
+            Err(Error::InvalidId(_))
+        ));
     }
 
-    // DELETING TASK
     #[test]
-    fn test_delete_task() {
+    fn test_select_done_and_pending() {
         let mut tf = get_test_task_file();
-        tf.delete_task("2.2");
-        tf.delete_task("1");
+        tf.mark_tasks(&get_ids("1.2,2"), true).unwrap();
+        let select = |tf: &TaskFile, ids: &str| {
+            let ids: Vec<String> = tf
+                .select(&get_ids(ids))
+                .unwrap()
+                .iter()
+                .map(|i| i.to_string())
+                .collect();
+            ids.join(",")
+        };
+
+        assert_eq!(select(&tf, "done"), "1.2,2,2.1,2.2");
+        assert_eq!(select(&tf, "pending"), "1,1.1");
+        assert_eq!(select(&tf, "pending,2.1"), "1,1.1,2.1");
+
+        tf.mark_tasks(&get_ids("done"), false).unwrap();
+        assert!(matches!(
+            tf.select(&get_ids("done")),
+            Err(Error::OutOfBounds(_))
+        ));
+    }
 
+    // JOURNAL
+    #[test]
+    fn test_revert_and_redo_saved_operations() {
+        let dir = get_test_dir("journal");
+        let file = dir.join("tasks.json");
+        let mut tf = get_test_task_file();
+        tf.task_file_path = file.clone();
+        tf.save().unwrap();
+
+        let mut tf = TaskFile::load(None, Some(&file)).unwrap();
+        tf.delete_tasks(&get_ids("1")).unwrap();
+        tf.mark_tasks(&get_ids("1.1"), true).unwrap();
+        tf.save().unwrap();
+        drop(tf);
+
+        let mut tf = TaskFile::load(None, Some(&file)).unwrap();
+        assert!(tf.revert(2).is_err());
+        assert!(tf.revert(1).is_ok());
+        assert_eq!(tf.tasks.len(), 2);
+        tf.save().unwrap();
+        drop(tf);
+
+        let mut tf = TaskFile::load(None, Some(&file)).unwrap();
+        assert!(tf.redo(1).is_ok());
         assert_eq!(tf.tasks.len(), 1);
-        assert_eq!(tf.tasks[0].subtasks.len(), 1)
+        assert!(tf.tasks[0].subtasks[0].done);
+        drop(tf);
+
+        fs::remove_dir_all(dir).unwrap();
     }
 
     #[test]
-    fn test_clear_dones() {
+    fn test_revert_refuses_unrecorded_changes() {
+        let dir = get_test_dir("journal_diverged");
+        let file = dir.join("tasks.json");
+        let mut tf = get_test_task_file();
+        tf.task_file_path = file.clone();
+        tf.save().unwrap();
+
+        let mut tf = TaskFile::load(None, Some(&file)).unwrap();
+        tf.delete_tasks(&get_ids("1")).unwrap();
+        tf.save().unwrap();
+        drop(tf);
+
+        TaskFile::restore_backup(&file, 1).unwrap();
+        let mut tf = TaskFile::load(None, Some(&file)).unwrap();
+        assert!(matches!(tf.revert(1), Err(Error::Command(_))));
+        assert!(matches!(tf.redo(1), Err(Error::Command(_))));
+        assert_eq!(tf.tasks.len(), 2);
+        drop(tf);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_revert_clear_empties_archive() {
+        let dir = get_test_dir("journal_archive");
+        let file = dir.join("tasks.json");
         let mut tf = get_test_task_file();
-        tf.tasks[1].subtasks[0].done = true;
         tf.tasks[0].done = true;
-        tf.clear_dones();
+        tf.task_file_path = file.clone();
+        tf.save().unwrap();
 
-        assert_eq!(tf.tasks.len(), 1);
-        assert_eq!(tf.tasks[0].subtasks.len(), 1);
+        let mut tf = TaskFile::load(None, Some(&file)).unwrap();
+        tf.clear_dones();
+        tf.save().unwrap();
+        tf.edit_tasks(&get_ids("1"), "edited").unwrap();
+        tf.save().unwrap();
+        drop(tf);
+
+        let mut tf = TaskFile::load(None, Some(&file)).unwrap();
+        assert!(tf.revert(2).is_ok());
+        assert!(tf.archive.is_empty());
+        assert!(tf.redo(1).is_ok());
+        assert_eq!(tf.archive.len(), 1);
+        assert!(tf.redo(1).is_ok());
+        assert_eq!(tf.archive.len(), 1);
+        drop(tf);
+
+        fs::remove_dir_all(dir).unwrap();
     }
 }
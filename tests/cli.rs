@@ -0,0 +1,160 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+/// Returns the path of a task file that only the test `name` uses.
+fn get_test_file(name: &str) -> PathBuf {
+    let file = env::temp_dir().join(format!("tsk_it_{name}_{}.json", process::id()));
+    let _ = fs::remove_file(&file);
+    file
+}
+
+/// Remove a test's task file along with its lock, journal and backups.
+fn remove_test_file(file: &Path) {
+    let prefix = file.to_string_lossy().into_owned();
+    for f in fs::read_dir(env::temp_dir()).unwrap() {
+        let path = f.unwrap().path();
+        if path.to_string_lossy().starts_with(&prefix) {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
+
+/// Run tsk without colors on the task file given by `TSK_FILE`, returns stdout.
+fn tsk(file: &Path, args: &[&str]) -> String {
+    tsk_with_input(file, args, "")
+}
+
+/// Same as `tsk`, with `input` piped to stdin.
+fn tsk_with_input(file: &Path, args: &[&str], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tsk"))
+        .args(args)
+        .arg("-c")
+        .env("TSK_FILE", file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Unable to run tsk");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(input.as_bytes()).unwrap();
+    drop(stdin);
+    let out = child.wait_with_output().unwrap();
+
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).unwrap()
+}
+
+/// Run tsk expecting it to fail, returns its exit code.
+fn tsk_exit_code(file: &Path, args: &[&str]) -> i32 {
+    let out = Command::new(env!("CARGO_BIN_EXE_tsk"))
+        .args(args)
+        .arg("-c")
+        .env("TSK_FILE", file)
+        .stdin(Stdio::null())
+        .output()
+        .expect("Unable to run tsk");
+
+    assert!(!out.status.success());
+    out.status.code().unwrap()
+}
+
+#[test]
+fn test_env_var_selects_task_file() {
+    let file = get_test_file("env_var");
+    tsk(&file, &["add", "one"]);
+    let out = tsk(&file, &["do", "1"]);
+
+    assert!(out.contains("1. [X] one"));
+    assert!(fs::read_to_string(&file).unwrap().contains("\"one\""));
+
+    remove_test_file(&file);
+}
+
+#[test]
+fn test_file_option_overrides_env_var() {
+    let file = get_test_file("file_opt");
+    let other = get_test_file("file_opt_other");
+
+    let opt = format!("--file={}", other.display());
+    tsk(&file, &[&opt, "add", "other"]);
+
+    assert!(!file.exists());
+    assert!(fs::read_to_string(&other).unwrap().contains("\"other\""));
+
+    remove_test_file(&other);
+}
+
+#[test]
+fn test_notes_from_stdin() {
+    let file = get_test_file("notes");
+    tsk(&file, &["add", "one"]);
+
+    let out = tsk_with_input(&file, &["note", "1"], "first line\nsecond line\n");
+    assert!(out.contains("1. [ ] one [notes]"));
+
+    let out = tsk(&file, &["show", "1"]);
+    assert!(out.starts_with("1. [ ] one\n"));
+    assert!(out.ends_with("\nfirst line\nsecond line\n"));
+
+    remove_test_file(&file);
+}
+
+#[test]
+fn test_exit_codes() {
+    let file = get_test_file("exit_codes");
+    tsk(&file, &["add", "one"]);
+
+    assert_eq!(tsk_exit_code(&file, &["stop"]), 1);
+    assert_eq!(tsk_exit_code(&file, &["print", "--by=size"]), 2);
+    assert_eq!(tsk_exit_code(&file, &["due", "1", "someday"]), 2);
+    assert_eq!(tsk_exit_code(&file, &["do", "1..2..3"]), 3);
+    assert_eq!(tsk_exit_code(&file, &["do", "9"]), 4);
+
+    fs::write(&file, "{ not json").unwrap();
+    assert_eq!(tsk_exit_code(&file, &["print"]), 6);
+
+    remove_test_file(&file);
+}
+
+#[test]
+fn test_ids_from_the_end() {
+    let file = get_test_file("ids_from_end");
+    for t in ["three", "two", "one"] {
+        tsk(&file, &["add", t]);
+    }
+
+    // A new task file starts with a done task, at the end.
+    let out = tsk(&file, &["do", "-2"]);
+    assert!(out.contains("3. [X] three"));
+    let out = tsk(&file, &["do", "pending"]);
+    assert!(out.contains("1. [X] one"));
+    assert!(out.contains("2. [X] two"));
+
+    remove_test_file(&file);
+}
+
+#[test]
+fn test_delete_and_move_id_lists() {
+    let file = get_test_file("id_lists");
+    for t in ["four", "three", "two", "one"] {
+        tsk(&file, &["add", t]);
+    }
+
+    // Moved tasks keep the order they were in.
+    let out = tsk(&file, &["move", "3,1", "3"]);
+    assert!(out.starts_with("1. [ ] two\n2. [ ] four\n3. [ ] one\n4. [ ] three\n"));
+    // `-1` is the done task a new task file starts with.
+    let out = tsk(&file, &["delete", "1..2,-1"]);
+    assert_eq!(out, "1. [ ] one\n2. [ ] three\n");
+
+    remove_test_file(&file);
+}